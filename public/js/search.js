@@ -4,6 +4,8 @@ const POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER = '{post_date_timestamp}';
 const POST_ITEM_DATE_READABLE_PLACEHOLDER = '{post_date_human_readable}';
 const POST_ITEM_TITLE_PLACEHOLDER = '{post_title}';
 const POST_ITEM_EXCERPT_PLACEHOLDER = '{post_excerpt}';
+const POST_ITEM_READING_TIME_PLACEHOLDER = '{post_reading_time}';
+const POST_ITEM_WORD_COUNT_PLACEHOLDER = '{post_word_count}';
 
 const QUERY_PARAMETER_KEY = "query";
 
@@ -45,7 +47,9 @@ const getResultItemMarkup = match => {
         .replace(POST_ITEM_TITLE_PLACEHOLDER, match.title)
         .replace(POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER, match.dateTimestamp)
         .replace(POST_ITEM_DATE_READABLE_PLACEHOLDER, match.dateHumanReadable)
-        .replace(POST_ITEM_EXCERPT_PLACEHOLDER, match.excerpt ? match.excerpt : "");
+        .replace(POST_ITEM_EXCERPT_PLACEHOLDER, match.excerpt ? match.excerpt : "")
+        .replace(POST_ITEM_READING_TIME_PLACEHOLDER, match.readingTime)
+        .replace(POST_ITEM_WORD_COUNT_PLACEHOLDER, match.wordCount);
 }
 
 const ITEM_TEMPLATE = `<li class="archive-list-item">
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use grass::OutputStyle;
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use sha2::{Digest, Sha256};
+
+// {asset "css/app.css"} prints the URL of css/app.css, fingerprinted or not
+pub const ASSET_HELPER_START: &str = "{asset \"";
+const ASSET_HELPER_END: &str = "\"}";
+// Hex characters of the content hash added to fingerprinted file names
+const FINGERPRINT_LENGTH: usize = 8;
+
+/// Reads a `true`/`false` environment variable, false when it is not set
+pub fn flag(name: &str) -> Result<bool, String> {
+    match dotenv::var(name) {
+        Ok(value) => match value.trim() {
+            "true" => Ok(true),
+            "false" | "" => Ok(false),
+            _ => Err(format!("{} must be true or false, got \"{}\"", name, value)),
+        },
+        Err(_) => Ok(false),
+    }
+}
+
+/// How static files are transformed on their way to `build/`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AssetOptions {
+    /// Minify CSS and JS files (`MINIFY_ASSETS`)
+    pub minify: bool,
+    /// Add a content hash to CSS and JS file names (`FINGERPRINT_ASSETS`)
+    pub fingerprint: bool,
+}
+
+impl AssetOptions {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(AssetOptions {
+            minify: flag("MINIFY_ASSETS")?,
+            fingerprint: flag("FINGERPRINT_ASSETS")?,
+        })
+    }
+}
+
+fn extension(path: &str) -> Option<&str> {
+    Path::new(path).extension().and_then(|extension| extension.to_str())
+}
+
+fn minify_css(source: &str) -> Result<String, String> {
+    let mut stylesheet = StyleSheet::parse(source, ParserOptions::default()).map_err(|error| error.to_string())?;
+    stylesheet.minify(MinifyOptions::default()).map_err(|error| error.to_string())?;
+    let css = stylesheet
+        .to_css(PrinterOptions {
+            minify: true,
+            ..PrinterOptions::default()
+        })
+        .map_err(|error| error.to_string())?;
+    Ok(css.code)
+}
+
+fn minify_js(source: &[u8]) -> Result<Vec<u8>, String> {
+    let session = minify_js::Session::new();
+    let mut output = Vec::new();
+    minify_js::minify(&session, minify_js::TopLevelMode::Global, source, &mut output)
+        .map_err(|error| error.to_string())?;
+    Ok(output)
+}
+
+/// Minifies CSS and JS files, other files are returned unchanged
+pub fn minify(path: &str, contents: Vec<u8>) -> Result<Vec<u8>, String> {
+    let minified = match extension(path) {
+        Some("css") => {
+            let source = String::from_utf8(contents).map_err(|_| format!("{} is not valid UTF-8", path))?;
+            minify_css(&source).map(String::into_bytes)
+        }
+        Some("js") => minify_js(&contents),
+        _ => return Ok(contents),
+    };
+    minified.map_err(|error| format!("Failed to minify {}: {}", path, error))
+}
+
+/// Adds the start of the SHA-256 hash of `contents` before the extension:
+/// `css/app.css` becomes `css/app.3f9a1c0d.css`
+pub fn fingerprinted_path(path: &str, contents: &[u8]) -> String {
+    let hash = format!("{:x}", Sha256::digest(contents));
+    let hash = &hash[..FINGERPRINT_LENGTH];
+    match path.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !stem.ends_with('/') => {
+            format!("{}.{}.{}", stem, hash, extension)
+        }
+        _ => format!("{}.{}", path, hash),
+    }
+}
+
+/// Minifies and fingerprints a static file according to `options`. Returns
+/// its output path below `build/` and its contents.
+pub fn process(path: &str, contents: Vec<u8>, options: AssetOptions) -> Result<(String, Vec<u8>), String> {
+    let is_code = matches!(extension(path), Some("css" | "js"));
+    if !is_code {
+        return Ok((path.to_string(), contents));
+    }
+
+    let contents = if options.minify { minify(path, contents)? } else { contents };
+    let output_path = if options.fingerprint {
+        fingerprinted_path(path, &contents)
+    } else {
+        path.to_string()
+    };
+    Ok((output_path, contents))
+}
+
+/// Compiles the SCSS entry point at `path` with grass. `@use` and `@import`
+/// resolve next to the importing file, then in `load_path`. Development
+/// builds keep the CSS expanded, others compress it. grass cannot write
+/// source maps, so none are generated.
+pub fn compile_scss(path: &Path, load_path: &Path, development: bool) -> Result<String, String> {
+    let style = if development { OutputStyle::Expanded } else { OutputStyle::Compressed };
+    let options = grass::Options::default().style(style).load_path(load_path);
+    grass::from_path(path, &options).map_err(|error| match error.clone().kind() {
+        // Point at the file that failed, which may be a partial the entry point imports
+        grass::ErrorKind::ParseError { message, loc, .. } => format!(
+            "{}:{}:{}: {}",
+            loc.file.name(),
+            loc.begin.line + 1,
+            loc.begin.column + 1,
+            message
+        ),
+        _ => format!("{}: {}", path.display(), error),
+    })
+}
+
+/// Replaces every `{asset "path"}` helper with the URL of the file's output
+/// path, failing on files that are not in `assets`
+pub fn resolve_helpers(template: &str, assets: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(ASSET_HELPER_START) {
+        let path_start = start + ASSET_HELPER_START.len();
+        let path_length = rest[path_start..]
+            .find(ASSET_HELPER_END)
+            .ok_or_else(|| format!("Unclosed {}...{} helper", ASSET_HELPER_START, ASSET_HELPER_END))?;
+        let path = &rest[path_start..path_start + path_length];
+        let output_path = assets
+            .get(path.trim_start_matches('/'))
+            .ok_or_else(|| format!("Unknown asset \"{}\", add it to public/", path))?;
+
+        output.push_str(&rest[..start]);
+        output.push('/');
+        output.push_str(output_path);
+        rest = &rest[path_start + path_length + ASSET_HELPER_END.len()..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_minify() {
+        let css = "/* Colors */\nbody {\n    color: #ff0000;\n    margin: 0px;\n}\n";
+        assert_eq!(minify("css/app.css", css.as_bytes().to_vec()).unwrap(), b"body{color:red;margin:0}");
+
+        let js = "// Greeting\nconst greet = name => {\n    return `Hello\n  ${name}`;\n};\n";
+        assert_eq!(minify("js/app.js", js.as_bytes().to_vec()).unwrap(), b"const greet=a=>`Hello\n  ${a}`");
+
+        assert_eq!(minify("img/logo.svg", b"<svg> </svg>".to_vec()).unwrap(), b"<svg> </svg>");
+        assert!(minify("js/broken.js", b"const = ;".to_vec()).unwrap_err().contains("js/broken.js"));
+    }
+
+    #[test]
+    fn test_process_fingerprints_code_only() {
+        let options = AssetOptions {
+            minify: false,
+            fingerprint: true,
+        };
+
+        let (path, _) = process("css/app.css", b"body{}".to_vec(), options).unwrap();
+        assert_eq!(path, "css/app.7c98040a.css");
+        assert_ne!(path, process("css/app.css", b"p{}".to_vec(), options).unwrap().0);
+        assert_eq!(process("img/logo.png", b"png".to_vec(), options).unwrap().0, "img/logo.png");
+        assert_eq!(process("css/app.css", b"body{}".to_vec(), AssetOptions::default()).unwrap().0, "css/app.css");
+    }
+
+    #[test]
+    fn test_compile_scss() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("_colors.scss"), "$accent: #ff0000;\n").unwrap();
+        fs::write(dir.path().join("app.scss"), "@use 'colors';\nbody {\n  a { color: colors.$accent; }\n}\n").unwrap();
+        fs::write(dir.path().join("broken.scss"), "@use 'colors';\nbody {\n  width: 1px +;\n}\n").unwrap();
+
+        assert_eq!(compile_scss(&dir.path().join("app.scss"), dir.path(), false).unwrap(), "body a{color:red}");
+        assert_eq!(
+            compile_scss(&dir.path().join("app.scss"), dir.path(), true).unwrap(),
+            "body a {\n  color: #ff0000;\n}\n"
+        );
+
+        let error = compile_scss(&dir.path().join("broken.scss"), dir.path(), false).unwrap_err();
+        assert!(error.ends_with("broken.scss:3:10: Expected expression."), "{}", error);
+    }
+
+    #[test]
+    fn test_resolve_helpers() {
+        let assets = BTreeMap::from([("js/search.js".to_string(), "js/search.1a2b3c4d.js".to_string())]);
+
+        assert_eq!(
+            resolve_helpers(r#"<script src="{asset "js/search.js"}"></script>"#, &assets).unwrap(),
+            r#"<script src="/js/search.1a2b3c4d.js"></script>"#
+        );
+        assert!(resolve_helpers(r#"{asset "css/app.css"}"#, &assets)
+            .unwrap_err()
+            .contains("Unknown asset \"css/app.css\""));
+    }
+}
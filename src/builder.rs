@@ -1,22 +1,111 @@
+use std::env;
+
+use crate::output;
+use crate::parser::PublicationState;
 use crate::plugin::{PluginPipeline, Site};
 use crate::plugins::*;
 
-pub fn build() -> Result<(), Box<dyn std::error::Error>> {
+const BUILD_DIR: &str = "build";
+
+/// Command line flags that change which content ends up in the build
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BuildOptions {
+    /// Render drafts with a preview banner and `noindex` meta (`--drafts`)
+    pub drafts: bool,
+    /// Render posts dated in the future (`--future`)
+    pub future: bool,
+    /// List the files that would be written instead of writing them (`--dry-run`)
+    pub dry_run: bool,
+    /// Keep compiled stylesheets expanded and skip minification for debugging (`--dev`)
+    pub dev: bool,
+    /// Print how long each plugin took (`--verbose`)
+    pub verbose: bool,
+}
+
+impl BuildOptions {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = BuildOptions::default();
+        for arg in args {
+            match arg.as_str() {
+                "--drafts" => options.drafts = true,
+                "--future" => options.future = true,
+                "--dry-run" => options.dry_run = true,
+                "--dev" => options.dev = true,
+                "--verbose" => options.verbose = true,
+                unknown => return Err(format!("Unknown build flag: {}", unknown)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Returns true if a post or page in the given state should be rendered
+    /// at all. Expired content keeps its page, it is only dropped from listings.
+    pub fn should_render(&self, state: PublicationState) -> bool {
+        match state {
+            PublicationState::Published | PublicationState::Expired => true,
+            PublicationState::Draft => self.drafts,
+            PublicationState::Scheduled => self.future,
+        }
+    }
+}
+
+pub fn build(options: BuildOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let site = render_site(options)?;
+
+    if options.dry_run {
+        output::print_dry_run(&site);
+    } else {
+        output::write(&site, &env::current_dir()?.join(BUILD_DIR))?;
+    }
+
+    Ok(())
+}
+
+/// Runs every plugin and returns the site with its output tree filled in
+pub fn render_site(options: BuildOptions) -> Result<Site, Box<dyn std::error::Error>> {
     let mut site = Site::new();
     let mut pipeline = PluginPipeline::new();
+    pipeline.set_verbose(options.verbose);
 
-    // Add plugins in the order they should run
-    pipeline.add_plugin(BuildPlugin::new());
-    pipeline.add_plugin(PostsPlugin::new());
+    // The pipeline orders plugins by what they require and provide, loaders
+    // come first so that `on_content_loaded` fires before anything renders
+    pipeline.add_plugin(BuildPlugin::new(options));
+    pipeline.add_plugin(DataPlugin::new());
+    pipeline.add_plugin(I18nPlugin::load()?);
+    pipeline.add_plugin(LinksPlugin::new());
+    pipeline.add_plugin(PostsPlugin::new(options));
+    pipeline.add_plugin(PagesPlugin::new(options));
+    pipeline.add_plugin(RelatedPlugin::new());
     pipeline.add_plugin(PostPlugin::new());
     pipeline.add_plugin(HomepagePlugin::new());
-    pipeline.add_plugin(PagesPlugin::new());
+    pipeline.add_plugin(SeriesPlugin::new());
+    pipeline.add_plugin(ArchivePlugin::new());
+    pipeline.add_plugin(PagePlugin::new());
+    pipeline.add_plugin(AliasesPlugin::new());
     pipeline.add_plugin(SearchPlugin::new());
     pipeline.add_plugin(FeedPlugin::new());
     pipeline.add_plugin(SitemapPlugin::new());
 
+    // Scripts from scripts/ and executables from EXTERNAL_PLUGINS run once content is loaded
+    if let Some(plugin) = ScriptPlugin::load()? {
+        pipeline.add_plugin(plugin);
+    }
+    for plugin in ExternalPlugin::from_config() {
+        pipeline.add_plugin(plugin);
+    }
+    // Added after every plugin that renders pages so that it minifies them once the
+    // others have transformed them, development builds keep pages readable
+    if let Some(plugin) = MinifyPlugin::from_config()?.filter(|_| !options.dev) {
+        pipeline.add_plugin(plugin);
+    }
+    // The audit runs in `on_build_finished`, so it sees the final pages, minified
+    // or not, wherever it is added
+    if let Some(plugin) = AccessibilityPlugin::from_config()? {
+        pipeline.add_plugin(plugin);
+    }
+
     // Run the pipeline
     pipeline.run(&mut site)?;
 
-    Ok(())
+    Ok(site)
 }
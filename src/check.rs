@@ -0,0 +1,445 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use markdown::mdast::Node;
+use serde::Serialize;
+
+use crate::builder::{self, BuildOptions};
+use crate::content;
+use crate::html::start_tags;
+use crate::parser::{self, ContentKind, FrontmatterMap};
+use crate::plugin::Site;
+use crate::schema::Schema;
+
+const POSTS_DIR: &str = "posts";
+const PAGES_DIR: &str = "pages";
+// Issues about the whole site, such as a failed build, are reported against the site directory
+const SITE_FILE: &str = ".";
+// Search engines cut meta descriptions around this length
+const DESCRIPTION_MAX_LENGTH: usize = 160;
+
+/// Errors fail the check, warnings are only reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found by `check`, reported against the file to fix
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Issue {
+    /// Markdown file of the post or page, the output path of generated pages,
+    /// or `.` for a build that failed
+    pub file: String,
+    pub severity: Severity,
+    /// Name of the check that failed, e.g. "broken-link"
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Issue {
+    fn new(file: &str, severity: Severity, rule: &'static str, message: String) -> Self {
+        Issue {
+            file: file.to_string(),
+            severity,
+            rule,
+            message,
+        }
+    }
+}
+
+/// How `check` prints its findings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// One line per issue and a summary
+    Human,
+    /// A JSON array of issues
+    Json,
+}
+
+/// Returns the ids of a page's elements, the targets of `#fragment` links
+fn anchors(html: &str) -> HashSet<String> {
+    start_tags(html)
+        .into_iter()
+        .flat_map(|tag| tag.attributes)
+        .filter(|(attribute, _)| attribute == "id" || attribute == "name")
+        .map(|(_, value)| value)
+        .collect()
+}
+
+/// Resolves a link on the page at `page_path` to a path below `build/` and
+/// a fragment. Links to other sites and schemes like `mailto:` return None.
+fn internal_target(link: &str, page_path: &str, host: &str) -> Option<(String, String)> {
+    let link = match link.trim().strip_prefix(host) {
+        Some("") => "/",
+        Some(rest) if rest.starts_with(['/', '?', '#']) => rest,
+        _ => link.trim(),
+    };
+    let scheme_end = link.find(':');
+    let path_start = link.find(['/', '?', '#']);
+    if link.starts_with("//") || scheme_end.is_some_and(|end| path_start.is_none_or(|start| end < start)) {
+        return None;
+    }
+
+    let (link, fragment) = link.split_once('#').unwrap_or((link, ""));
+    let path = link.split('?').next().unwrap_or("");
+    if path.is_empty() {
+        return Some((page_path.to_string(), fragment.to_string()));
+    }
+
+    // Relative links start from the page's directory
+    let base = if path.starts_with('/') { "" } else { page_path.rsplit_once('/').map_or("", |(dir, _)| dir) };
+    let mut segments: Vec<&str> = base.split('/').filter(|segment| !segment.is_empty()).collect();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some((segments.join("/"), fragment.to_string()))
+}
+
+/// Finds the output file served at `path`, a file or a directory with an `index.html`
+fn find_file<'a>(output: &'a BTreeMap<String, Vec<u8>>, path: &str) -> Option<&'a str> {
+    let index = if path.is_empty() { "index.html".to_string() } else { format!("{}/index.html", path) };
+    [path.to_string(), index]
+        .iter()
+        .find_map(|candidate| output.get_key_value(candidate))
+        .map(|(file, _)| file.as_str())
+}
+
+/// Checks that every internal `href` and `src` of the generated HTML points
+/// at a file of the output tree, and `#fragment`s at an element of that file
+pub fn check_links(site: &Site, host: &str) -> Vec<Issue> {
+    let output = site.output();
+    let mut page_anchors: HashMap<&str, HashSet<String>> = HashMap::new();
+    let mut issues = Vec::new();
+
+    for (page_path, contents) in output.iter().filter(|(path, _)| path.ends_with(".html")) {
+        let html = String::from_utf8_lossy(contents);
+        let mut checked = HashSet::new();
+        for (_, link) in start_tags(&html)
+            .into_iter()
+            .flat_map(|tag| tag.attributes)
+            .filter(|(attribute, _)| attribute == "href" || attribute == "src")
+        {
+            let Some((path, fragment)) = internal_target(&link, page_path, host) else {
+                continue;
+            };
+            if !checked.insert(link.clone()) {
+                continue;
+            }
+
+            let (rule, message) = match find_file(output, &path) {
+                None => ("broken-link", format!("broken link {}", link)),
+                Some(file) if !fragment.is_empty() && file.ends_with(".html") => {
+                    let ids = page_anchors
+                        .entry(file)
+                        .or_insert_with(|| anchors(&String::from_utf8_lossy(&output[file])));
+                    if ids.contains(&fragment) {
+                        continue;
+                    }
+                    ("missing-anchor", format!("missing anchor #{} for link {}", fragment, link))
+                }
+                Some(_) => continue,
+            };
+            // Point generated pages at themselves, and posts and pages at their markdown file
+            let file = site.source_file(page_path);
+            let message = if file == *page_path { message } else { format!("{} in {}", message, page_path) };
+            issues.push(Issue::new(&file, Severity::Error, rule, message));
+        }
+    }
+    issues
+}
+
+/// Returns the frontmatter of a markdown file as raw YAML values
+fn read_frontmatter(source: &str) -> Result<FrontmatterMap, String> {
+    let ast = markdown::to_mdast(source, &content::parse_options())
+        .map_err(|error| format!("Failed to parse markdown: {}", error))?;
+    match ast.children().and_then(|children| children.first()) {
+        Some(Node::Yaml(yaml)) => parser::parse_frontmatter_map(&yaml.value),
+        _ => Err("No frontmatter found".to_string()),
+    }
+}
+
+/// Validates the frontmatter of one post or page against the schema
+fn check_frontmatter(schema: &Schema, file: &str, source: &str, kind: ContentKind) -> Vec<Issue> {
+    match read_frontmatter(source) {
+        Ok(frontmatter) => schema
+            .validate(&frontmatter, kind)
+            .into_iter()
+            .map(|problem| Issue::new(file, Severity::Error, "frontmatter", problem))
+            .collect(),
+        Err(error) => vec![Issue::new(file, Severity::Error, "frontmatter", error)],
+    }
+}
+
+/// Collects the markdown files of `dir`, and of its subdirectories if `recursive`
+fn markdown_files(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() && recursive {
+            markdown_files(&path, recursive, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Validates the frontmatter of every post and page, drafts included
+fn check_content_files(schema: &Schema) -> Result<Vec<Issue>, Box<dyn std::error::Error>> {
+    let mut issues = Vec::new();
+    for (dir, recursive, kind) in [(POSTS_DIR, false, ContentKind::Post), (PAGES_DIR, true, ContentKind::Page)] {
+        let mut files = Vec::new();
+        markdown_files(Path::new(dir), recursive, &mut files)?;
+        files.sort();
+        for file in files {
+            let source = fs::read_to_string(&file)?;
+            issues.extend(check_frontmatter(schema, &file.to_string_lossy(), &source, kind));
+        }
+    }
+    Ok(issues)
+}
+
+/// Lints the rendered posts and pages: descriptions, image alt texts,
+/// duplicate titles and empty posts
+fn lint_content(site: &Site) -> Vec<Issue> {
+    let contents = site
+        .posts
+        .iter()
+        .map(|post| (&post.content, ContentKind::Post))
+        .chain(site.pages.iter().map(|page| (&page.content, ContentKind::Page)));
+
+    let mut issues = Vec::new();
+    let mut titles: HashMap<(&str, String), &str> = HashMap::new();
+    for (content, kind) in contents {
+        let file = content.full_path.as_str();
+        let warning = |rule, message| Issue::new(file, Severity::Warning, rule, message);
+
+        match content.frontmatter.description.as_deref().map(str::trim) {
+            None | Some("") => issues.push(warning("missing-description", "missing description".to_string())),
+            Some(description) if description.chars().count() > DESCRIPTION_MAX_LENGTH => issues.push(warning(
+                "long-description",
+                format!(
+                    "description is {} characters long, search engines show about {}",
+                    description.chars().count(),
+                    DESCRIPTION_MAX_LENGTH
+                ),
+            )),
+            Some(_) => {}
+        }
+
+        for tag in start_tags(&content.html).into_iter().filter(|tag| tag.name == "img") {
+            if tag.attribute("alt").is_none_or(|alt| alt.trim().is_empty()) {
+                let src = tag.attribute("src").unwrap_or_default();
+                issues.push(warning("image-alt", format!("image {} has no alt text", src)));
+            }
+        }
+
+        let title = (content.lang.as_str(), content.frontmatter.title.trim().to_lowercase());
+        if let Some(other) = titles.insert(title, file) {
+            issues.push(warning(
+                "duplicate-title",
+                format!("title \"{}\" is also used by {}", content.frontmatter.title, other),
+            ));
+        }
+
+        if kind == ContentKind::Post && content.word_count == 0 && !content.html.contains("<img") {
+            issues.push(warning("empty-content", "post has no content".to_string()));
+        }
+    }
+    issues
+}
+
+/// Splits `--format human|json` off the build flags
+fn parse_args(args: &[String]) -> Result<(Format, BuildOptions), String> {
+    let mut format = Format::Human;
+    let mut build_args = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--format" {
+            format = match args.next().map(String::as_str) {
+                Some("human") => Format::Human,
+                Some("json") => Format::Json,
+                other => return Err(format!("--format must be human or json, got {:?}", other.unwrap_or(""))),
+            };
+        } else {
+            build_args.push(arg.clone());
+        }
+    }
+    Ok((format, BuildOptions::from_args(&build_args)?))
+}
+
+/// Validates frontmatter, lints content and checks the links of the site
+/// built in memory, for `check`. Fails if any error was found.
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (format, options) = parse_args(args)?;
+    let schema = Schema::from_env()?;
+    let host = dotenv::var("HOST").expect("HOST environment variable must be set");
+
+    // A build that fails is reported along with the problems found in the files
+    let mut issues = check_content_files(&schema)?;
+    let site = match builder::render_site(options) {
+        Ok(site) => {
+            issues.extend(lint_content(&site));
+            issues.extend(check_links(&site, &host));
+            Some(site)
+        }
+        Err(error) => {
+            issues.push(Issue::new(SITE_FILE, Severity::Error, "build", error.to_string()));
+            None
+        }
+    };
+
+    let errors = issues.iter().filter(|issue| issue.severity == Severity::Error).count();
+    let warnings = issues.len() - errors;
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&issues)?),
+        Format::Human => {
+            for issue in &issues {
+                let severity = match issue.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                println!("{}: {}: {} [{}]", issue.file, severity, issue.message, issue.rule);
+            }
+            if let (true, Some(site)) = (issues.is_empty(), &site) {
+                let page_count = site.output().keys().filter(|path| path.ends_with(".html")).count();
+                println!("No problems in {} posts and pages or {} HTML files", site.posts.len() + site.pages.len(), page_count);
+            } else {
+                println!("Found {} errors and {} warnings", errors, warnings);
+            }
+        }
+    }
+
+    if errors > 0 {
+        return Err(format!("check failed with {} errors", errors).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Post;
+
+    const HOST: &str = "https://example.com";
+
+    #[test]
+    fn test_internal_target() {
+        let target = |link: &str| internal_target(link, "docs/setup/index.html", HOST);
+        let resolved = |path: &str, fragment: &str| Some((path.to_string(), fragment.to_string()));
+
+        assert_eq!(target("https://example.com"), resolved("", ""));
+        assert_eq!(target("https://example.com/hello/#intro"), resolved("hello", "intro"));
+        assert_eq!(target("/css/styles.css?v=1.0"), resolved("css/styles.css", ""));
+        assert_eq!(target("../install/"), resolved("docs/install", ""));
+        assert_eq!(target("#usage"), resolved("docs/setup/index.html", "usage"));
+        assert_eq!(target("https://example.community/"), None);
+        assert_eq!(target("//cdn.example.com/app.js"), None);
+        assert_eq!(target("mailto:jane@example.com"), None);
+    }
+
+    #[test]
+    fn test_check_links() {
+        let mut site = Site::new();
+        site.claim_path("hello", "posts/2024-01-15-hello.md").unwrap();
+        site.add_file("css/app.css", "");
+        site.add_file(
+            "hello/index.html",
+            concat!(
+                r#"<link rel="stylesheet" href="/css/app.css"><a href="https://example.com/">Home</a>"#,
+                r##"<a href="/missing/">Gone</a><a href="#footnote-1">1</a><a href="#top">Top</a>"##,
+                r#"<a href="https://example.org/missing/">Elsewhere</a><p id="footnote-1"></p>"#
+            ),
+        );
+        site.add_file("index.html", r##"<a href="/hello/#footnote-1">Hello</a> <a href="/missing/">Gone</a>"##);
+
+        let issues = check_links(&site, HOST);
+
+        assert_eq!(
+            issues,
+            vec![
+                Issue::new(
+                    "posts/2024-01-15-hello.md",
+                    Severity::Error,
+                    "broken-link",
+                    "broken link /missing/ in hello/index.html".to_string()
+                ),
+                Issue::new(
+                    "posts/2024-01-15-hello.md",
+                    Severity::Error,
+                    "missing-anchor",
+                    "missing anchor #top for link #top in hello/index.html".to_string()
+                ),
+                Issue::new("index.html", Severity::Error, "broken-link", "broken link /missing/".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_frontmatter() {
+        let schema = Schema::with_extra_fields("").unwrap();
+
+        let issues = check_frontmatter(&schema, "posts/a.md", "---\ntitle: A\npubish: draft\n---\n", ContentKind::Post);
+
+        let messages: Vec<&str> = issues.iter().map(|issue| issue.message.as_str()).collect();
+        assert_eq!(messages, vec!["unknown field pubish, did you mean publish?", "missing required field date"]);
+        assert!(issues.iter().all(|issue| issue.file == "posts/a.md" && issue.severity == Severity::Error));
+        assert_eq!(
+            check_frontmatter(&schema, "posts/b.md", "No frontmatter\n", ContentKind::Post)[0].message,
+            "No frontmatter found"
+        );
+    }
+
+    #[test]
+    fn test_lint_content() {
+        let post = |file: &str, frontmatter: &str, body: &str| {
+            Post::for_test(&format!("---\ndate: 2024-01-15\n{}---\n\n{}", frontmatter, body), &format!("posts/{}", file))
+        };
+        let mut site = Site::new();
+        site.posts = vec![
+            post("a.md", "title: Hello\ndescription: Greetings\n", "![](/img/a.png) and ![A cat](/img/cat.png)"),
+            post("b.md", &format!("title: hello\ndescription: {}\n", "x".repeat(161)), "Text."),
+            post("c.md", "title: Empty\n", ""),
+        ];
+
+        let issues = lint_content(&site);
+        let rules: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|issue| (issue.file.as_str(), issue.rule))
+            .collect();
+
+        assert_eq!(
+            rules,
+            vec![
+                ("posts/a.md", "image-alt"),
+                ("posts/b.md", "long-description"),
+                ("posts/b.md", "duplicate-title"),
+                ("posts/c.md", "missing-description"),
+                ("posts/c.md", "empty-content"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |args: &[&str]| parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>());
+
+        assert_eq!(args(&[]).unwrap().0, Format::Human);
+        let (format, options) = args(&["--drafts", "--format", "json", "--verbose"]).unwrap();
+        assert_eq!(format, Format::Json);
+        assert!(options.drafts);
+        assert!(options.verbose);
+        assert!(args(&["--format", "xml"]).is_err());
+    }
+}
@@ -44,3 +44,85 @@ pub fn create_post() -> Result<String, Box<dyn std::error::Error>> {
 
     Ok(file_path.to_string_lossy().to_string())
 }
+
+const FIXTURE_DIR: &str = "bench-site";
+
+const FIXTURE_POST_TEMPLATE: &str = r#"---
+title: "Synthetic post {index}"
+description: Generated post number {index} for benchmarking
+keywords: benchmark, synthetic, post-{index}
+date: {date}
+---
+
+<section>
+
+## Introduction to post {index}
+
+Lorem ipsum dolor sit amet, **consectetur** adipiscing elit. Sed do eiusmod
+tempor incididunt ut labore et dolore magna aliqua. See [the homepage](/).
+
+- First item
+- Second item with `inline code`
+- Third item
+
+| Column | Value |
+| ------ | ----- |
+| index  | {index} |
+
+```rust
+fn main() {
+    println!("post {index}");
+}
+```
+
+</section>
+"#;
+
+/// Generates a throwaway site in `bench-site/` with `count` synthetic posts,
+/// reusing the templates, public files and `.env` of the current site
+pub fn generate_fixture(count: usize) -> Result<String, Box<dyn std::error::Error>> {
+    let fixture_path = Path::new(FIXTURE_DIR);
+    if fixture_path.exists() {
+        fs::remove_dir_all(fixture_path)?;
+    }
+    let posts_path = fixture_path.join(POSTS_DIR);
+    fs::create_dir_all(&posts_path)?;
+    fs::create_dir_all(fixture_path.join("pages"))?;
+
+    // Spread the posts over the days before today
+    let today = Local::now().date_naive();
+    for index in 0..count {
+        let date = (today - chrono::Duration::days(index as i64)).format("%Y-%m-%d").to_string();
+        let content = FIXTURE_POST_TEMPLATE
+            .replace("{index}", &index.to_string())
+            .replace("{date}", &date);
+
+        let mut file = File::create(posts_path.join(format!("{}-synthetic-post-{}.md", date, index)))?;
+        write!(file, "{}", content)?;
+    }
+
+    for dir in ["assets", "public"] {
+        if Path::new(dir).exists() {
+            copy_dir_recursive(Path::new(dir), &fixture_path.join(dir))?;
+        }
+    }
+    let env_file = if Path::new(".env").exists() { ".env" } else { ".env.example" };
+    fs::copy(env_file, fixture_path.join(".env"))?;
+
+    Ok(fixture_path.to_string_lossy().to_string())
+}
+
+fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+        if src_path.is_dir() {
+            copy_dir_recursive(&src_path, &dst_path)?;
+        } else {
+            fs::copy(&src_path, &dst_path)?;
+        }
+    }
+    Ok(())
+}
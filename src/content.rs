@@ -0,0 +1,411 @@
+use std::fs;
+use std::path::Path;
+
+use markdown::mdast::Node;
+use markdown::{Constructs, ParseOptions};
+
+use crate::i18n;
+use crate::math;
+use crate::parser::{self, ContentKind, FrontmatterData, PublicationState};
+use crate::render;
+
+// Words in an excerpt taken from the start of the text
+const EXCERPT_WORDS: usize = 40;
+// Average reading speed used for the reading time
+const WORDS_PER_MINUTE: usize = 200;
+// Everything before this comment is the excerpt
+const MORE_MARKER: &str = "<!-- more -->";
+
+/// A markdown file parsed once into its frontmatter, syntax tree and HTML.
+/// Posts and pages are built on top of it.
+#[derive(Debug)]
+pub struct Content {
+    pub file_name: String,
+    pub full_path: String,
+    pub frontmatter: FrontmatterData,
+    pub html: String,
+    pub state: PublicationState,
+    /// Words in the plain text of the body
+    pub word_count: usize,
+    /// Estimated minutes to read the body, at least 1
+    pub reading_time: usize,
+    /// Plain text before `<!-- more -->`, or the first words of the body
+    pub excerpt: String,
+    /// One of `LANGUAGES`, from the `lang` field, the file name suffix or the default language
+    pub lang: String,
+    /// Shared by the translations of the same post or page
+    pub translation_key: String,
+}
+
+impl Content {
+    /// The `description` if there is one, the excerpt otherwise
+    pub fn summary(&self) -> &str {
+        self.frontmatter.description.as_deref().unwrap_or(&self.excerpt)
+    }
+}
+
+/// Markdown options shared by every file: GFM plus YAML frontmatter
+pub fn parse_options() -> ParseOptions {
+    ParseOptions {
+        constructs: Constructs {
+            frontmatter: true,
+            ..Constructs::gfm()
+        },
+        ..ParseOptions::default()
+    }
+}
+
+/// Markdown options for files with `math: true`, adding `$...$` and `$$...$$`
+fn math_parse_options() -> ParseOptions {
+    let mut options = parse_options();
+    options.constructs.math_text = true;
+    options.constructs.math_flow = true;
+    options
+}
+
+/// Returns the YAML between the `---` fences that open a markdown file, so
+/// the frontmatter can be read before choosing the markdown options
+fn frontmatter_yaml(markdown_content: &str) -> Option<&str> {
+    let mut lines = markdown_content.split_inclusive('\n');
+    let opening = lines.next()?;
+    if opening.trim_end() != "---" {
+        return None;
+    }
+    let start = opening.len();
+    let mut end = start;
+    for line in lines {
+        if line.trim_end() == "---" {
+            return Some(markdown_content[start..end].trim_end_matches(['\r', '\n']));
+        }
+        end += line.len();
+    }
+    None
+}
+
+/// Reads and parses a markdown file, see `parse`
+pub fn load(path: &Path, kind: ContentKind, languages: &[String]) -> Result<Content, String> {
+    let markdown_content = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("file_name error")
+        .to_owned();
+
+    parse(&markdown_content, &path.to_string_lossy(), &file_name, kind, languages)
+}
+
+/// Appends the text of `node` to `text`, with a space after every block.
+/// Returns true once `<!-- more -->` is reached if `stop_at_marker` is set.
+fn collect_text(node: &Node, text: &mut String, stop_at_marker: bool) -> bool {
+    match node {
+        Node::Html(html) if stop_at_marker && html.value.trim() == MORE_MARKER => return true,
+        Node::Text(node) => text.push_str(&node.value),
+        Node::InlineCode(node) => text.push_str(&node.value),
+        Node::Code(node) => text.push_str(&node.value),
+        Node::Break(_) => text.push(' '),
+        // Frontmatter and raw HTML are not part of the text
+        Node::Yaml(_) | Node::Html(_) => {}
+        _ => {
+            for child in node.children().into_iter().flatten() {
+                if collect_text(child, text, stop_at_marker) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    // Inline nodes like emphasis and links run on with the text around them
+    if matches!(node, Node::Paragraph(_) | Node::Heading(_) | Node::Code(_) | Node::TableCell(_)) {
+        text.push(' ');
+    }
+    false
+}
+
+/// Returns the word count, reading time and excerpt of a document
+fn text_stats(ast: &Node) -> (usize, usize, String) {
+    let mut text = String::new();
+    collect_text(ast, &mut text, false);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let reading_time = words.len().div_ceil(WORDS_PER_MINUTE).max(1);
+
+    let mut before_marker = String::new();
+    let excerpt = if collect_text(ast, &mut before_marker, true) {
+        before_marker.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else if words.len() > EXCERPT_WORDS {
+        format!("{}…", words[..EXCERPT_WORDS].join(" "))
+    } else {
+        words.join(" ")
+    };
+
+    (words.len(), reading_time, excerpt)
+}
+
+/// Parses markdown source into `Content`. `languages` are the site's
+/// languages, default first, as returned by `i18n::languages`.
+pub fn parse(
+    markdown_content: &str,
+    full_path: &str,
+    file_name: &str,
+    kind: ContentKind,
+    languages: &[String],
+) -> Result<Content, String> {
+    let yaml = frontmatter_yaml(markdown_content).ok_or_else(|| "No frontmatter found".to_string())?;
+    let frontmatter = parser::parse_frontmatter_data(yaml, kind)?;
+
+    // Dollar signs are plain text unless the file opts into math
+    let options = if frontmatter.math { math_parse_options() } else { parse_options() };
+    let mut ast = markdown::to_mdast(markdown_content, &options)
+        .map_err(|err| format!("Failed to parse markdown: {}", err))?;
+    if frontmatter.math {
+        math::render_nodes(&mut ast).map_err(|err| format!("{}:{}", full_path, err))?;
+    }
+
+    let html = render::to_html(&ast);
+    let state = frontmatter.publication_state(&parser::today());
+    let (word_count, reading_time, excerpt) = text_stats(&ast);
+
+    // `hello.es.md` is the Spanish translation of `hello.md`
+    let (name, suffix) = i18n::split_language_suffix(file_name.trim_end_matches(".md"), languages);
+    let lang = frontmatter
+        .lang
+        .clone()
+        .or_else(|| suffix.map(str::to_string))
+        .unwrap_or_else(|| languages[0].clone());
+    if !languages.contains(&lang) {
+        return Err(format!("Unknown language {}, add it to LANGUAGES", lang));
+    }
+    let translation_key = frontmatter.translation_key.clone().unwrap_or_else(|| name.to_string());
+
+    Ok(Content {
+        file_name: file_name.to_string(),
+        full_path: full_path.to_string(),
+        frontmatter,
+        html,
+        state,
+        word_count,
+        reading_time,
+        excerpt,
+        lang,
+        translation_key,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn languages() -> Vec<String> {
+        vec!["en".to_string()]
+    }
+
+    fn html_of(body: &str) -> String {
+        let markdown_content = format!("---\ntitle: Test\ndate: 2024-01-01\n---\n\n{}", body);
+        parse(&markdown_content, "posts/test.md", "test.md", ContentKind::Post, &languages())
+            .unwrap()
+            .html
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = html_of("# Test\n\nThis is a **test**.");
+        assert_eq!(html, "<h1>Test</h1>\n<p>This is a <strong>test</strong>.</p>");
+    }
+
+    #[test]
+    fn test_render_matches_reference_renderer() {
+        let body = r#"## Lists
+
+- one
+- two
+  1. nested
+  2. list
+
+* [ ] todo
+* [x] done
+
+- tight with code
+  ```sh
+  cargo build
+
+  cargo test
+  ```
+- after
+
+Loose:
+
+- a
+
+- b
+
+> quote with `code` and [a link](https://example.com "Title")
+
+```rust
+fn main() {}
+```
+
+![alt *text*](/img/a.png)
+
+[reference][ref] and <https://autolink.example>
+
+[ref]: /target
+
+<section>
+
+Raw *HTML* mix
+
+</section>
+
+---
+
+Line
+break and ~~strike~~ with a note[^note] and another[^2].
+
+[^note]: The note.
+[^2]: Second *note*."#;
+
+        let options = markdown::Options {
+            compile: markdown::CompileOptions {
+                allow_dangerous_html: true,
+                ..markdown::CompileOptions::gfm()
+            },
+            parse: parse_options(),
+        };
+        let markdown_content = format!("---\ntitle: Test\ndate: 2024-01-01\n---\n\n{}", body);
+        let expected = markdown::to_html_with_options(&markdown_content, &options).unwrap();
+
+        assert_eq!(html_of(body), expected);
+    }
+
+    #[test]
+    fn test_render_gfm_tables_and_strikethrough() {
+        let html = html_of("| a | b |\n| :- | -: |\n| 1 | 2 |\n\n~~gone~~");
+
+        assert!(html.contains(r#"<th align="left">a</th>"#));
+        assert!(html.contains(r#"<td align="right">2</td>"#));
+        assert!(html.contains("<del>gone</del>"));
+    }
+
+    #[test]
+    fn test_render_drops_unsafe_urls() {
+        let html = html_of("[click](javascript:alert(1)) [ok](/a:b)");
+
+        assert!(html.contains(r#"<a href="">click</a>"#));
+        assert!(html.contains(r#"<a href="/a:b">ok</a>"#));
+    }
+
+    #[test]
+    fn test_render_math() {
+        assert_eq!(html_of("It costs $5 or $10."), "<p>It costs $5 or $10.</p>");
+
+        let math = |body: &str| {
+            let markdown_content = format!("---\ntitle: Test\ndate: 2024-01-01\nmath: true\n---\n\n{}", body);
+            parse(&markdown_content, "posts/test.md", "test.md", ContentKind::Post, &languages()).map(|content| content.html)
+        };
+        let html = math("Euler: $e^{i\\pi} + 1 = 0$\n\n$$\n\\int_0^1 x\\,dx\n$$").unwrap();
+        assert!(html.starts_with("<p>Euler: <span class=\"katex\"><math"), "{}", html);
+        assert!(html.contains("<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\">"), "{}", html);
+        assert!(!html.contains('$'));
+
+        let error = math("Fine.\n\nBroken: $\\frac{1}$").unwrap_err();
+        assert!(error.starts_with("posts/test.md:9:9: invalid TeX \"\\frac{1}\": KaTeX parse error:"), "{}", error);
+    }
+
+    #[test]
+    fn test_frontmatter_yaml() {
+        assert_eq!(frontmatter_yaml("---\ntitle: A\nmath: true\n---\n\nBody"), Some("title: A\nmath: true"));
+        assert_eq!(frontmatter_yaml("--- \r\ntitle: A\r\n---"), Some("title: A"));
+        assert_eq!(frontmatter_yaml("---\n---\n"), Some(""));
+        assert_eq!(frontmatter_yaml("---\ntitle: A\n"), None);
+        assert_eq!(frontmatter_yaml("# Title\n---\ntitle: A\n---\n"), None);
+    }
+
+    #[test]
+    fn test_parse_page_without_date() {
+        let content = parse(
+            "---\ntitle: About\n---\n\nHello",
+            "pages/about.md",
+            "about.md",
+            ContentKind::Page,
+            &languages(),
+        )
+        .unwrap();
+
+        assert_eq!(content.frontmatter.title, "About");
+        assert_eq!(content.frontmatter.date, "");
+        assert_eq!(content.html, "<p>Hello</p>");
+    }
+
+    #[test]
+    fn test_text_stats() {
+        let body = "# Intro\n\nHello *world*, this is `code`.\n\n- one\n- two\n\n<div>raw</div>";
+        let content = parse(
+            &format!("---\ntitle: Test\ndate: 2024-01-01\n---\n\n{}", body),
+            "posts/test.md",
+            "test.md",
+            ContentKind::Post,
+            &languages(),
+        )
+        .unwrap();
+
+        assert_eq!(content.word_count, 8);
+        assert_eq!(content.reading_time, 1);
+        assert_eq!(content.excerpt, "Intro Hello world, this is code. one two");
+
+        let long = "word ".repeat(450);
+        let content = parse(
+            &format!("---\ntitle: Test\ndate: 2024-01-01\n---\n\n{}", long),
+            "posts/test.md",
+            "test.md",
+            ContentKind::Post,
+            &languages(),
+        )
+        .unwrap();
+
+        assert_eq!(content.word_count, 450);
+        assert_eq!(content.reading_time, 3);
+        assert_eq!(content.excerpt, format!("{}…", "word ".repeat(EXCERPT_WORDS).trim_end()));
+    }
+
+    #[test]
+    fn test_excerpt_stops_at_more_marker() {
+        let content = parse(
+            "---\ntitle: Test\ndate: 2024-01-01\ndescription: Given\n---\n\nFirst part.\n\n<!-- more -->\n\nThe rest.",
+            "posts/test.md",
+            "test.md",
+            ContentKind::Post,
+            &languages(),
+        )
+        .unwrap();
+
+        assert_eq!(content.excerpt, "First part.");
+        assert_eq!(content.word_count, 4);
+        // The description wins over the excerpt
+        assert_eq!(content.summary(), "Given");
+    }
+
+    #[test]
+    fn test_language_from_suffix_and_frontmatter() {
+        let site_languages = vec!["en".to_string(), "es".to_string()];
+        let markdown = "---\ntitle: Hola\ndate: 2024-01-01\n---\n\nHola";
+
+        let content = parse(markdown, "posts/hola.es.md", "hola.es.md", ContentKind::Post, &site_languages).unwrap();
+        assert_eq!((content.lang.as_str(), content.translation_key.as_str()), ("es", "hola"));
+
+        let content = parse(markdown, "posts/hola.md", "hola.md", ContentKind::Post, &site_languages).unwrap();
+        assert_eq!(content.lang, "en");
+
+        // Only the languages passed in count
+        let content = parse(markdown, "posts/hola.es.md", "hola.es.md", ContentKind::Post, &languages()).unwrap();
+        assert_eq!((content.lang.as_str(), content.translation_key.as_str()), ("en", "hola.es"));
+        let error = parse(
+            "---\ntitle: Hola\ndate: 2024-01-01\nlang: fr\n---\n",
+            "posts/hola.md",
+            "hola.md",
+            ContentKind::Post,
+            &site_languages,
+        )
+        .unwrap_err();
+        assert!(error.contains("Unknown language fr"), "{}", error);
+    }
+}
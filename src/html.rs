@@ -0,0 +1,251 @@
+/// An HTML start tag, names lowercased and attribute values decoded
+#[derive(Debug, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A piece of an HTML document, as read by `tokens`
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Start(Tag),
+    /// A closing tag, by lowercased name
+    End(String),
+    /// Text between tags, with entities decoded
+    Text(String),
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn tag_name_length(rest: &str) -> usize {
+    rest.find(|character: char| !character.is_ascii_alphanumeric()).unwrap_or(rest.len())
+}
+
+/// Reads the attributes of a start tag up to its `>`, returning them with
+/// the rest of the document
+fn read_attributes(mut rest: &str) -> (Vec<(String, String)>, &str) {
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+
+        let attribute_length = rest
+            .find(|character: char| character.is_whitespace() || matches!(character, '=' | '>' | '/'))
+            .unwrap_or(rest.len());
+        if attribute_length == 0 {
+            // A `/` of a self-closing tag or a stray `=`
+            rest = &rest[1..];
+            continue;
+        }
+        let attribute = rest[..attribute_length].to_ascii_lowercase();
+        rest = rest[attribute_length..].trim_start();
+
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).map_or(after.len(), |end| end + 1);
+                    value = &after[1..end];
+                    rest = after.get(end + 1..).unwrap_or("");
+                }
+                _ => {
+                    let end = after
+                        .find(|character: char| character.is_whitespace() || character == '>')
+                        .unwrap_or(after.len());
+                    value = &after[..end];
+                    rest = &after[end..];
+                }
+            }
+        }
+        attributes.push((attribute, decode_entities(value)));
+    }
+    (attributes, rest)
+}
+
+/// Splits an HTML document into start tags, closing tags and text, skipping
+/// comments, doctypes and the contents of `<script>` and `<style>`
+pub fn tokens(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let token = if let Some(comment) = after.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            None
+        } else if after.starts_with(['!', '?']) {
+            rest = after.find('>').map_or("", |end| &after[end + 1..]);
+            None
+        } else if let Some(closing) = after
+            .strip_prefix('/')
+            .filter(|closing| closing.starts_with(|character: char| character.is_ascii_alphabetic()))
+        {
+            let name = closing[..tag_name_length(closing)].to_ascii_lowercase();
+            rest = closing.find('>').map_or("", |end| &closing[end + 1..]);
+            Some(Token::End(name))
+        } else if after.starts_with(|character: char| character.is_ascii_alphabetic()) {
+            let name_length = tag_name_length(after);
+            let name = after[..name_length].to_ascii_lowercase();
+            let (attributes, after_tag) = read_attributes(&after[name_length..]);
+            rest = after_tag;
+            if name == "script" || name == "style" {
+                let closing_tag = format!("</{}", name);
+                rest = rest.to_ascii_lowercase().find(&closing_tag).map_or("", |end| &rest[end..]);
+            }
+            Some(Token::Start(Tag { name, attributes }))
+        } else {
+            // A lone `<` in text
+            text.push('<');
+            rest = after;
+            continue;
+        };
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(decode_entities(&std::mem::take(&mut text))));
+        }
+        tokens.extend(token);
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(Token::Text(decode_entities(&text)));
+    }
+    tokens
+}
+
+/// Reads the start tags of an HTML document
+pub fn start_tags(html: &str) -> Vec<Tag> {
+    tokens(html)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Start(tag) => Some(tag),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the position and name of the first `<pre>` or `<code>` start tag
+fn find_code_start(html: &str) -> Option<(usize, &'static str)> {
+    let lowercase = html.to_ascii_lowercase();
+    ["pre", "code"]
+        .into_iter()
+        .filter_map(|name| {
+            let tag = format!("<{}", name);
+            lowercase
+                .match_indices(&tag)
+                .map(|(start, _)| start)
+                .find(|start| {
+                    lowercase[start + tag.len()..].starts_with(|character: char| character == '>' || character.is_whitespace())
+                })
+                .map(|start| (start, name))
+        })
+        .min()
+}
+
+/// Passes the parts of `html` outside `<pre>` and `<code>` elements through
+/// `replace`, so that code samples showing template syntax stay as written
+pub fn replace_outside_code<E>(html: &str, mut replace: impl FnMut(&str) -> Result<String, E>) -> Result<String, E> {
+    let mut output = String::new();
+    let mut rest = html;
+    while let Some((start, name)) = find_code_start(rest) {
+        output.push_str(&replace(&rest[..start])?);
+        let closing_tag = format!("</{}>", name);
+        let end = rest[start..]
+            .to_ascii_lowercase()
+            .find(&closing_tag)
+            .map_or(rest.len(), |end| start + end + closing_tag.len());
+        output.push_str(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    output.push_str(&replace(rest)?);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, attributes: &[(&str, &str)]) -> Tag {
+        Tag {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(attribute, value)| (attribute.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_start_tags() {
+        let html = concat!(
+            r#"<!doctype html><!-- <a href="/commented/"> --><a class=x href="/a/?q=1&amp;p=2">A</a>"#,
+            r#"<img src=/img/a.png alt=""/><script>if (a <b) { x = "<a href='/js/'>"; }</script>"#,
+            r#"<p id='intro'>1 < 2</p>"#
+        );
+
+        assert_eq!(
+            start_tags(html),
+            vec![
+                tag("a", &[("class", "x"), ("href", "/a/?q=1&p=2")]),
+                tag("img", &[("src", "/img/a.png"), ("alt", "")]),
+                tag("script", &[]),
+                tag("p", &[("id", "intro")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokens() {
+        let html = "<p>Fish &amp; chips<br/>1 < 2</P><!-- note --><style>p { color: red; }</style>";
+
+        assert_eq!(
+            tokens(html),
+            vec![
+                Token::Start(tag("p", &[])),
+                Token::Text("Fish & chips".to_string()),
+                Token::Start(tag("br", &[])),
+                Token::Text("1 < 2".to_string()),
+                Token::End("p".to_string()),
+                Token::Start(tag("style", &[])),
+                Token::End("style".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_outside_code() {
+        let html = "<p>{x} <code>{x}</code></p><pre><code class=\"language-liquid\">{x}\n</code></pre><CODE>{x}</CODE><p>{x}</p>";
+
+        let replaced = replace_outside_code(html, |text| Ok::<_, ()>(text.replace("{x}", "y"))).unwrap();
+
+        assert_eq!(
+            replaced,
+            "<p>y <code>{x}</code></p><pre><code class=\"language-liquid\">{x}\n</code></pre><CODE>{x}</CODE><p>y</p>"
+        );
+        assert_eq!(replace_outside_code("<codex>{x}</codex>", |text| Ok::<_, ()>(text.replace("{x}", "y"))), Ok("<codex>y</codex>".to_string()));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::permalink;
+
+/// Used when `LANGUAGES` is not set
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Directory of the UI string tables, one `<language>.yaml` per language
+pub const STRINGS_DIR: &str = "i18n";
+
+/// UI strings of one language, by dotted key such as `nav.about`
+pub type Strings = HashMap<String, String>;
+
+// Month names are looked up as `months.january` and so on, with these English
+// names for languages that don't have them
+const MONTH_KEYS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november",
+    "december",
+];
+const MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November",
+    "December",
+];
+
+/// Returns the site's languages from `LANGUAGES`, e.g. `en,es`. The first one
+/// is the default language, which is served without a URL prefix.
+pub fn languages() -> Vec<String> {
+    let languages: Vec<String> = dotenv::var("LANGUAGES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|language| language.trim().to_string())
+        .filter(|language| !language.is_empty())
+        .collect();
+    if languages.is_empty() {
+        vec![DEFAULT_LANGUAGE.to_string()]
+    } else {
+        languages
+    }
+}
+
+/// Splits a language suffix off a file stem: `hello.es` is `hello` in `es`.
+/// Only the site's languages count, so `notes.old` keeps its name.
+pub fn split_language_suffix<'a>(stem: &'a str, languages: &[String]) -> (&'a str, Option<&'a str>) {
+    match stem.rsplit_once('.') {
+        Some((name, suffix)) if languages.iter().any(|language| language == suffix) => (name, Some(suffix)),
+        _ => (stem, None),
+    }
+}
+
+/// Returns the output path of `path` in `language`: unchanged for the default
+/// language, below `<language>/` for the others
+pub fn localize(language: &str, path: &str, default_language: &str) -> String {
+    match (language == default_language, path.is_empty()) {
+        (true, _) => path.to_string(),
+        (false, true) => language.to_string(),
+        (false, false) => format!("{}/{}", language, path),
+    }
+}
+
+/// Returns the language of an output path from its first directory
+pub fn language_of_path<'a>(path: &str, languages: &'a [String]) -> &'a str {
+    let first_segment = path.split('/').next().unwrap_or("");
+    languages[1..]
+        .iter()
+        .find(|language| language.as_str() == first_segment)
+        .unwrap_or(&languages[0])
+}
+
+/// Renders `<link rel="alternate" hreflang>` tags for the translations of a
+/// post or page, given as (language, output path). Empty without translations.
+pub fn render_hreflang_links(host: &str, translations: &[(&str, &str)]) -> String {
+    if translations.len() < 2 {
+        return String::new();
+    }
+    translations
+        .iter()
+        .map(|(language, path)| {
+            format!(
+                r#"<link rel="alternate" hreflang="{}" href="{}">"#,
+                language,
+                permalink::url(host, path)
+            )
+        })
+        .collect()
+}
+
+/// Returns the name of `month` (1 to 12) in `language`, falling back to the
+/// default language and then to English
+pub fn month_name(strings: &HashMap<String, Strings>, language: &str, default_language: &str, month: u32) -> String {
+    let index = (month as usize).clamp(1, 12) - 1;
+    let key = format!("months.{}", MONTH_KEYS[index]);
+    [language, default_language]
+        .iter()
+        .find_map(|language| strings.get(*language)?.get(&key))
+        .cloned()
+        .unwrap_or_else(|| MONTH_NAMES[index].to_string())
+}
+
+/// Flattens nested YAML maps into dotted keys
+fn flatten(prefix: &str, value: &serde_yaml::Value, strings: &mut Strings) -> Result<(), String> {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, value) in map {
+                let key = key.as_str().ok_or_else(|| format!("Key under {} is not a string", prefix))?;
+                let key = if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+                flatten(&key, value, strings)?;
+            }
+        }
+        serde_yaml::Value::String(text) => {
+            strings.insert(prefix.to_string(), text.clone());
+        }
+        serde_yaml::Value::Number(number) => {
+            strings.insert(prefix.to_string(), number.to_string());
+        }
+        serde_yaml::Value::Bool(flag) => {
+            strings.insert(prefix.to_string(), flag.to_string());
+        }
+        _ => return Err(format!("{} must be a string", prefix)),
+    }
+    Ok(())
+}
+
+/// Reads `<dir>/<language>.yaml` for every language that has one
+pub fn load_strings(dir: &Path, languages: &[String]) -> Result<HashMap<String, Strings>, String> {
+    let mut tables = HashMap::new();
+    for language in languages {
+        let path = dir.join(format!("{}.yaml", language));
+        if !path.is_file() {
+            continue;
+        }
+
+        let source = fs::read_to_string(&path).map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(&source).map_err(|error| format!("Failed to load {}: {}", path.display(), error))?;
+        let mut strings = Strings::new();
+        flatten("", &value, &mut strings).map_err(|error| format!("{}: {}", path.display(), error))?;
+        tables.insert(language.clone(), strings);
+    }
+    Ok(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn languages() -> Vec<String> {
+        vec!["en".to_string(), "es".to_string()]
+    }
+
+    #[test]
+    fn test_split_language_suffix() {
+        assert_eq!(split_language_suffix("hello.es", &languages()), ("hello", Some("es")));
+        assert_eq!(split_language_suffix("hello", &languages()), ("hello", None));
+        assert_eq!(split_language_suffix("notes.old", &languages()), ("notes.old", None));
+    }
+
+    #[test]
+    fn test_localize() {
+        assert_eq!(localize("en", "hello", "en"), "hello");
+        assert_eq!(localize("es", "hola", "en"), "es/hola");
+        assert_eq!(localize("es", "", "en"), "es");
+        assert_eq!(language_of_path("es/hola/index.html", &languages()), "es");
+        assert_eq!(language_of_path("estate/index.html", &languages()), "en");
+        assert_eq!(language_of_path("index.html", &languages()), "en");
+    }
+
+    #[test]
+    fn test_load_strings() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("es.yaml"), "nav:\n  about: Sobre mí\nposts: 3\n").unwrap();
+
+        let tables = load_strings(dir.path(), &languages()).unwrap();
+
+        assert!(!tables.contains_key("en"));
+        assert_eq!(tables["es"]["nav.about"], "Sobre mí");
+        assert_eq!(tables["es"]["posts"], "3");
+
+        fs::write(dir.path().join("en.yaml"), "nav: [a, b]\n").unwrap();
+        assert!(load_strings(dir.path(), &languages()).is_err());
+    }
+
+    #[test]
+    fn test_month_name() {
+        let tables = HashMap::from([("es".to_string(), Strings::from([("months.march".to_string(), "marzo".to_string())]))]);
+
+        assert_eq!(month_name(&tables, "es", "en", 3), "marzo");
+        assert_eq!(month_name(&tables, "es", "en", 4), "April");
+        assert_eq!(month_name(&tables, "en", "en", 3), "March");
+    }
+}
@@ -1,8 +1,21 @@
+mod assets;
 mod builder;
+mod check;
 mod commands;
+mod content;
+mod html;
+mod i18n;
+mod math;
+mod output;
+mod pagination;
 mod parser;
+mod permalink;
 mod plugin;
 mod plugins;
+mod post_item;
+mod render;
+mod schema;
+mod slug;
 
 use std::env;
 
@@ -14,8 +27,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let file_path = commands::create_post()?;
         println!("Successfully created new post:");
         println!("{}", file_path);
+    } else if args.len() > 1 && args[1] == "bench-fixture" {
+        let count = match args.get(2) {
+            Some(count) => count.parse().map_err(|_| format!("Invalid number of posts: {}", count))?,
+            None => 1000,
+        };
+        let fixture_path = commands::generate_fixture(count)?;
+        println!("Generated {} synthetic posts in {}", count, fixture_path);
+        println!("Build it with: cd {} && cargo run --release --manifest-path ../Cargo.toml", fixture_path);
+    } else if args.len() > 1 && args[1] == "check" {
+        check::run(&args[2..])?;
     } else {
-        builder::build()?;
+        let options = builder::BuildOptions::from_args(&args[1..])?;
+        builder::build(options)?;
     }
 
     Ok(())
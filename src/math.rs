@@ -0,0 +1,83 @@
+use katex::{Opts, OutputType};
+use markdown::mdast::{Html, Node};
+use markdown::unist::Position;
+
+/// Renders TeX to MathML with KaTeX, so pages need neither JavaScript nor
+/// the KaTeX fonts. `display` renders a centered block instead of inline math.
+pub fn to_mathml(tex: &str, display: bool) -> Result<String, String> {
+    let options = Opts::builder()
+        .output_type(OutputType::Mathml)
+        .display_mode(display)
+        .throw_on_error(true)
+        .build()
+        .map_err(|error| error.to_string())?;
+    katex::render_with_opts(tex, &options).map_err(|error| match error {
+        katex::Error::JsExecError(detail) => parse_error_message(&detail),
+        error => error.to_string(),
+    })
+}
+
+/// The JS engine reports KaTeX's exception as a debug-formatted value,
+/// `String("ParseError: KaTeX parse error: ...")`. Returns the message.
+fn parse_error_message(detail: &str) -> String {
+    let message = detail
+        .strip_prefix("String(\"")
+        .and_then(|message| message.strip_suffix("\")"))
+        .unwrap_or(detail);
+    message
+        .trim_start_matches("ParseError: ")
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\")
+}
+
+fn location(position: &Option<Position>) -> String {
+    position
+        .as_ref()
+        .map(|position| format!("{}:{}: ", position.start.line, position.start.column))
+        .unwrap_or_default()
+}
+
+/// Replaces the `$...$` and `$$...$$` nodes of a syntax tree with their
+/// MathML. Errors point at the line and column of the invalid TeX.
+pub fn render_nodes(node: &mut Node) -> Result<(), String> {
+    let (tex, display, position) = match node {
+        Node::InlineMath(math) => (&math.value, false, &math.position),
+        Node::Math(math) => (&math.value, true, &math.position),
+        _ => {
+            for child in node.children_mut().into_iter().flatten() {
+                render_nodes(child)?;
+            }
+            return Ok(());
+        }
+    };
+    let mathml = to_mathml(tex, display)
+        .map_err(|error| format!("{}invalid TeX \"{}\": {}", location(position), tex, error))?;
+    *node = Node::Html(Html {
+        value: mathml,
+        position: position.clone(),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_mathml() {
+        let inline = to_mathml("x^2", false).unwrap();
+        assert!(inline.starts_with("<span class=\"katex\"><math"), "{}", inline);
+        assert!(inline.contains("<msup><mi>x</mi><mn>2</mn></msup>"), "{}", inline);
+        assert!(!inline.contains("display=\"block\""));
+
+        let display = to_mathml("\\frac{a}{b}", true).unwrap();
+        assert!(display.contains("display=\"block\""), "{}", display);
+        assert!(display.contains("<mfrac>"), "{}", display);
+
+        let error = to_mathml("\\frac{1}", false).unwrap_err();
+        assert_eq!(
+            error,
+            "KaTeX parse error: Unexpected end of input in a macro argument, expected '}' at end of input: \\frac{1}"
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use std::fs;
+use std::path::Path;
+
+use crate::plugin::Site;
+
+/// Replaces `build_dir` with the site's output tree
+pub fn write(site: &Site, build_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if build_dir.exists() {
+        fs::remove_dir_all(build_dir)?;
+    }
+    fs::create_dir_all(build_dir)?;
+
+    for (path, contents) in site.output() {
+        let output_path = build_dir.join(path);
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(output_path, contents)?;
+    }
+
+    println!("Wrote {} files to {}", site.output().len(), build_dir.display());
+    Ok(())
+}
+
+/// Lists what `write` would write, for `build --dry-run`
+pub fn print_dry_run(site: &Site) {
+    let total_bytes: usize = site.output().values().map(Vec::len).sum();
+    for (path, contents) in site.output() {
+        println!("build/{} ({} bytes)", path, contents.len());
+    }
+    println!(
+        "Dry run: {} files, {} bytes would be written",
+        site.output().len(),
+        total_bytes
+    );
+}
@@ -0,0 +1,92 @@
+use crate::permalink;
+
+/// Reads `POSTS_PER_PAGE`, the number of posts on one listing page. `None`
+/// when it is not set or 0, which keeps every listing on a single page.
+pub fn per_page() -> Result<Option<usize>, String> {
+    match dotenv::var("POSTS_PER_PAGE") {
+        Ok(count) => count
+            .trim()
+            .parse::<usize>()
+            .map(|count| Some(count).filter(|&count| count > 0))
+            .map_err(|_| format!("POSTS_PER_PAGE must be a number, got \"{}\"", count)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Splits a listing into pages. There is always at least one, possibly empty, page.
+pub fn paginate<T>(items: &[T], per_page: Option<usize>) -> Vec<&[T]> {
+    match per_page {
+        Some(per_page) if items.len() > per_page => items.chunks(per_page).collect(),
+        _ => vec![items],
+    }
+}
+
+/// Output path of page `number` (starting at 1) of the listing at `base`:
+/// the listing itself, then `base/page/2`, `base/page/3`...
+pub fn page_path(base: &str, number: usize) -> String {
+    match (number, base.is_empty()) {
+        (1, _) => base.to_string(),
+        (_, true) => format!("page/{}", number),
+        (_, false) => format!("{}/page/{}", base, number),
+    }
+}
+
+/// Renders the links between the pages of a listing, empty for a single page
+pub fn render_navigation(host: &str, base: &str, number: usize, page_count: usize) -> String {
+    if page_count < 2 {
+        return String::new();
+    }
+
+    let mut links = String::new();
+    if number > 1 {
+        links.push_str(&format!(
+            r#"<a class="newer-posts" href="{}" rel="prev">Newer posts</a>"#,
+            permalink::url(host, &page_path(base, number - 1))
+        ));
+    }
+    links.push_str(&format!(r#"<span class="page-number">Page {} of {}</span>"#, number, page_count));
+    if number < page_count {
+        links.push_str(&format!(
+            r#"<a class="older-posts" href="{}" rel="next">Older posts</a>"#,
+            permalink::url(host, &page_path(base, number + 1))
+        ));
+    }
+    format!(r#"<nav class="pagination" aria-label="Pagination">{}</nav>"#, links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let items = [1, 2, 3, 4, 5];
+
+        assert_eq!(paginate(&items, None), vec![&items[..]]);
+        assert_eq!(paginate(&items, Some(5)), vec![&items[..]]);
+        assert_eq!(paginate(&items, Some(2)), vec![&[1, 2][..], &[3, 4], &[5]]);
+        assert_eq!(paginate::<i32>(&[], Some(2)).len(), 1);
+    }
+
+    #[test]
+    fn test_page_path() {
+        assert_eq!(page_path("2024", 1), "2024");
+        assert_eq!(page_path("2024/03", 3), "2024/03/page/3");
+        assert_eq!(page_path("", 2), "page/2");
+    }
+
+    #[test]
+    fn test_render_navigation() {
+        assert_eq!(render_navigation("https://example.com", "archive", 1, 1), "");
+        assert_eq!(
+            render_navigation("https://example.com", "archive", 2, 3),
+            concat!(
+                r#"<nav class="pagination" aria-label="Pagination">"#,
+                r#"<a class="newer-posts" href="https://example.com/archive/" rel="prev">Newer posts</a>"#,
+                r#"<span class="page-number">Page 2 of 3</span>"#,
+                r#"<a class="older-posts" href="https://example.com/archive/page/3/" rel="next">Older posts</a>"#,
+                "</nav>"
+            )
+        );
+    }
+}
@@ -3,17 +3,73 @@ use std::{
     fs::{self, DirEntry},
 };
 
-use markdown::{mdast::Node, CompileOptions, Constructs, Options, ParseOptions};
+use chrono::Local;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::content::{self, Content};
+use crate::slug;
+
+/// Where a post sits in its publication lifecycle on a given day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicationState {
+    Published,
+    Draft,
+    /// Dated in the future
+    Scheduled,
+    /// Past its `expires` date
+    Expired,
+}
 
-#[derive(Debug)]
+impl PublicationState {
+    /// Returns true if the post belongs in listings, feeds and the sitemap
+    pub fn is_listed(&self) -> bool {
+        matches!(self, PublicationState::Published | PublicationState::Scheduled)
+    }
+}
+
+/// The kind of content a frontmatter block belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Post,
+    /// Pages share the post frontmatter but don't need a date
+    Page,
+}
+
+/// Frontmatter shared by posts and pages. Serialized with the keys written in
+/// the frontmatter, e.g. for external plugins.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FrontmatterData {
+    /// Old paths that should redirect to this post or page
+    pub aliases: Vec<String>,
+    /// Empty for pages without a date
     pub date: String,
     pub description: Option<String>,
+    pub expires: Option<String>,
+    /// Open Graph / Twitter card image, absolute or relative to the host
+    pub image: Option<String>,
     pub keywords: Option<String>,
+    /// Language code, one of `LANGUAGES`. Defaults to the file name suffix or the default language.
+    pub lang: Option<String>,
+    /// Template name in assets/templates/ used instead of the default one
+    pub layout: Option<String>,
+    #[serde(rename = "lightTheme")]
     pub light_theme: bool,
+    /// Renders `$...$` and `$$...$$` as math
+    pub math: bool,
     pub permalink: Option<String>,
     pub publish: Option<String>,
+    /// Name of the series the post belongs to
+    pub series: Option<String>,
+    /// Position in the series, posts without one follow by date
+    #[serde(rename = "seriesOrder")]
+    pub series_order: Option<u32>,
+    /// Topics used to find related posts
+    pub tags: Vec<String>,
     pub title: String,
+    /// Groups translations of the same post or page, defaults to the file name
+    #[serde(rename = "translationKey")]
+    pub translation_key: Option<String>,
 }
 
 impl FrontmatterData {
@@ -22,6 +78,30 @@ impl FrontmatterData {
         self.publish.as_deref() == Some("draft")
     }
 
+    /// Returns the publication state of the post on `today` (YYYY-MM-DD).
+    /// Only the date part of `date` and `expires` is compared.
+    pub fn publication_state(&self, today: &str) -> PublicationState {
+        if self.is_draft() {
+            return PublicationState::Draft;
+        }
+        if day_of(&self.date) > today {
+            return PublicationState::Scheduled;
+        }
+        match &self.expires {
+            Some(expires) if day_of(expires) < today => PublicationState::Expired,
+            _ => PublicationState::Published,
+        }
+    }
+
+    /// Returns the template file to render with, `default_template` unless
+    /// the frontmatter sets a `layout`
+    pub fn template_path(&self, default_template: &str) -> String {
+        match &self.layout {
+            Some(layout) => format!("{}/{}.html", TEMPLATES_DIR, layout),
+            None => default_template.to_string(),
+        }
+    }
+
     /// Returns the CSS class for the theme ("light-theme" or "")
     pub fn theme_class(&self) -> &str {
         if self.light_theme {
@@ -34,159 +114,203 @@ impl FrontmatterData {
 
 #[derive(Debug)]
 pub struct Post {
-    pub file_name: String,
-    pub frontmatter: FrontmatterData,
-    pub full_path: String,
-    pub html: String,
+    pub content: Content,
+    /// Output path below `build/`. Starts out as the slug and is resolved
+    /// against the site's permalink pattern by `PostsPlugin`.
     pub permalink: String,
+    /// The custom `permalink` from the frontmatter or the title slug
+    pub slug: String,
+    /// Index in `Site::posts` of the listed post published before this one
+    pub previous: Option<usize>,
+    /// Index in `Site::posts` of the listed post published after this one
+    pub next: Option<usize>,
+    /// Indexes in `Site::posts` of the most related listed posts, best match first
+    pub related: Vec<usize>,
+}
+
+impl Post {
+    pub fn new(content: Content) -> Self {
+        // Use custom permalink from frontmatter if provided, otherwise generate from title
+        let slug = match &content.frontmatter.permalink {
+            Some(custom) => custom.trim_matches('/').to_string(),
+            None => get_permalink_from_title(&content.frontmatter.title),
+        };
+
+        Post {
+            content,
+            permalink: slug.clone(),
+            slug,
+            previous: None,
+            next: None,
+            related: Vec::new(),
+        }
+    }
+
+    /// Parses `markdown` as the post at `path`, e.g. "posts/hello.md", in a
+    /// site in English and Spanish
+    #[cfg(test)]
+    pub fn for_test(markdown: &str, path: &str) -> Self {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        let languages = ["en".to_string(), "es".to_string()];
+        Post::new(content::parse(markdown, path, file_name, ContentKind::Post, &languages).unwrap())
+    }
 }
 
 const POSTS_FILE_PATH: &str = "posts";
+const TEMPLATES_DIR: &str = "./assets/templates";
+
+/// Returns the YYYY-MM-DD part of a frontmatter date
+pub fn day_of(date: &str) -> &str {
+    date.get(..10).unwrap_or(date)
+}
+
+/// Returns today's date in YYYY-MM-DD format
+pub fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
 
 fn get_permalink_from_title(post_title: &str) -> String {
-    post_title
-        .to_lowercase()
-        .replace(' ', "-")
-        .replace(',', "")
-        .replace('#', "")
-        .replace(':', "")
-        .replace('"', "")
-        .replace('\'', "")
+    slug::slugify(post_title)
 }
 
-fn parse_html(post_markdown: &String) -> String {
-    let parse_options = Options {
-        compile: CompileOptions {
-            allow_dangerous_html: true, // I need it for my mixed Markdown + HTML post style
-            ..CompileOptions::default()
-        },
-        parse: ParseOptions {
-            constructs: Constructs {
-                frontmatter: true,
-                ..Constructs::default()
-            },
-            ..ParseOptions::default()
-        },
-    };
-    let html = markdown::to_html_with_options(post_markdown, &parse_options).unwrap();
-    return html;
+/// Frontmatter keys mapped to their raw YAML values
+pub type FrontmatterMap = BTreeMap<String, serde_yaml::Value>;
+
+pub fn parse_frontmatter_map(yaml: &str) -> Result<FrontmatterMap, String> {
+    serde_yaml::from_str(yaml).map_err(|e| format!("YAML parsing error: {}", e))
 }
 
-fn parse_frontmatter_data(frontmatter_data: Node) -> Result<FrontmatterData, String> {
-    match frontmatter_data {
-        Node::Yaml(value) => {
-            let parsed_ast: BTreeMap<String, String> = serde_yaml::from_str(&value.value)
-                .map_err(|e| format!("YAML parsing error: {}", e))?;
-
-            let title_src = parsed_ast.get("title");
-            let parsed_title =
-                title_src.ok_or_else(|| "Missing required field: title".to_string())?;
-
-            let date_src = parsed_ast.get("date");
-            let parsed_date = date_src.ok_or_else(|| "Missing required field: date".to_string())?;
-
-            let parsed_keywords = parsed_ast.get("keywords");
-            let parsed_description = parsed_ast.get("description");
-            let parsed_publish = parsed_ast.get("publish");
-            let parsed_permalink = parsed_ast.get("permalink");
-            let parsed_light_theme = parsed_ast
-                .get("lightTheme")
-                .map(|v| v == "true")
-                .unwrap_or(false);
-
-            Ok(FrontmatterData {
-                title: parsed_title.to_string(),
-                description: parsed_description.cloned(),
-                keywords: parsed_keywords.cloned(),
-                light_theme: parsed_light_theme,
-                permalink: parsed_permalink.cloned(),
-                publish: parsed_publish.cloned(),
-                date: parsed_date.to_string(),
+/// Reads a scalar field as a string. Numbers and booleans are accepted so
+/// that `lightTheme: true` or `title: 1984` keep working.
+pub fn string_field(map: &FrontmatterMap, key: &str) -> Result<Option<String>, String> {
+    match map.get(key) {
+        None | Some(serde_yaml::Value::Null) => Ok(None),
+        Some(serde_yaml::Value::String(value)) => Ok(Some(value.clone())),
+        Some(serde_yaml::Value::Number(value)) => Ok(Some(value.to_string())),
+        Some(serde_yaml::Value::Bool(value)) => Ok(Some(value.to_string())),
+        Some(_) => Err(format!("YAML parsing error: {} must be a string", key)),
+    }
+}
+
+/// Reads a field that may be written either as a single string or as a list
+pub fn list_field(map: &FrontmatterMap, key: &str) -> Result<Vec<String>, String> {
+    match map.get(key) {
+        Some(serde_yaml::Value::Sequence(values)) => values
+            .iter()
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("YAML parsing error: {} must be a list of strings", key))
             })
-        }
-        _ => Err("there was an error parsing frontmatter data".to_owned()),
+            .collect(),
+        _ => Ok(string_field(map, key)?.into_iter().collect()),
     }
 }
 
-fn parse_post(post_path: DirEntry) -> Result<Post, String> {
-    let custom = Constructs {
-        frontmatter: true,
-        ..Constructs::gfm()
-    };
-    let parse_options = ParseOptions {
-        constructs: custom,
-        ..ParseOptions::default()
-    };
+/// A layout names a file in the templates directory, never a path
+pub fn check_layout(layout: &str) -> Result<(), String> {
+    if layout.contains(['/', '\\']) || layout.contains("..") {
+        return Err(format!("layout must be a template name without / or .., got \"{}\"", layout));
+    }
+    Ok(())
+}
 
-    let full_path = &post_path
-        .path()
-        .to_str()
-        .unwrap_or("file_path error")
-        .to_owned();
-    let file_name = post_path
-        .file_name()
-        .to_str()
-        .unwrap_or("file_name error")
-        .to_owned();
-    let post_markdown =
-        fs::read_to_string(String::from(full_path)).expect("should have read the file");
-
-    let parsed_post_html = parse_html(&post_markdown);
-
-    let parsed_ast_from_post = markdown::to_mdast(&post_markdown, &parse_options)
-        .map_err(|err| format!("Failed to parse markdown: {}", err))?;
-    let frontmatter_data = parsed_ast_from_post
-        .children()
-        .ok_or_else(|| "No children found in markdown".to_string())?
-        .clone()
-        .into_iter()
-        .nth(0)
-        .ok_or_else(|| "No frontmatter found".to_string())?;
-
-    let post_frontmatter = parse_frontmatter_data(frontmatter_data)?;
-
-    // Use custom permalink from frontmatter if provided, otherwise generate from title
-    let permalink = post_frontmatter
-        .permalink
-        .clone()
-        .unwrap_or_else(|| get_permalink_from_title(&post_frontmatter.title));
-
-    let new_post = Post {
-        file_name,
-        frontmatter: post_frontmatter,
-        full_path: String::from(full_path),
-        html: parsed_post_html,
-        permalink,
+/// Parses the YAML between the frontmatter fences of a post or page
+pub fn parse_frontmatter_data(yaml: &str, kind: ContentKind) -> Result<FrontmatterData, String> {
+    let parsed_ast = parse_frontmatter_map(yaml)?;
+
+    let title_src = string_field(&parsed_ast, "title")?;
+    let parsed_title = title_src.ok_or_else(|| "Missing required field: title".to_string())?;
+
+    let date_src = string_field(&parsed_ast, "date")?;
+    let parsed_date = match kind {
+        ContentKind::Post => date_src.ok_or_else(|| "Missing required field: date".to_string())?,
+        ContentKind::Page => date_src.unwrap_or_default(),
     };
-    return Ok(new_post);
+
+    let parsed_aliases = list_field(&parsed_ast, "aliases")?;
+    let parsed_keywords = string_field(&parsed_ast, "keywords")?;
+    let parsed_tags = list_field(&parsed_ast, "tags")?;
+    let parsed_description = string_field(&parsed_ast, "description")?;
+    let parsed_expires = string_field(&parsed_ast, "expires")?;
+    let parsed_image = string_field(&parsed_ast, "image")?;
+    let parsed_lang = string_field(&parsed_ast, "lang")?;
+    let parsed_layout = string_field(&parsed_ast, "layout")?;
+    if let Some(layout) = &parsed_layout {
+        check_layout(layout).map_err(|error| format!("YAML parsing error: {}", error))?;
+    }
+    let parsed_publish = string_field(&parsed_ast, "publish")?;
+    let parsed_permalink = string_field(&parsed_ast, "permalink")?;
+    let parsed_series = string_field(&parsed_ast, "series")?;
+    let parsed_series_order = string_field(&parsed_ast, "seriesOrder")?
+        .map(|order| order.parse::<u32>())
+        .transpose()
+        .map_err(|_| "YAML parsing error: seriesOrder must be a number".to_string())?;
+    let parsed_translation_key = string_field(&parsed_ast, "translationKey")?;
+    let parsed_light_theme = string_field(&parsed_ast, "lightTheme")?
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    let parsed_math = string_field(&parsed_ast, "math")?.is_some_and(|math| math == "true");
+
+    Ok(FrontmatterData {
+        aliases: parsed_aliases,
+        title: parsed_title,
+        description: parsed_description,
+        expires: parsed_expires,
+        image: parsed_image,
+        keywords: parsed_keywords,
+        lang: parsed_lang,
+        layout: parsed_layout,
+        light_theme: parsed_light_theme,
+        math: parsed_math,
+        permalink: parsed_permalink,
+        publish: parsed_publish,
+        series: parsed_series,
+        series_order: parsed_series_order,
+        tags: parsed_tags,
+        translation_key: parsed_translation_key,
+        date: parsed_date,
+    })
 }
 
-pub fn get_posts() -> Vec<Post> {
+fn parse_post(post_path: DirEntry, languages: &[String]) -> Result<Post, String> {
+    let content = content::load(&post_path.path(), ContentKind::Post, languages)?;
+    Ok(Post::new(content))
+}
+
+pub fn get_posts(languages: &[String]) -> Vec<Post> {
     let error_reading_files_message = format!("Error reading files at {}", POSTS_FILE_PATH);
     let post_paths = fs::read_dir(POSTS_FILE_PATH).expect(&error_reading_files_message);
 
-    let mut parsed_posts = Vec::<Post>::new();
-
+    let mut post_dir_entries = Vec::new();
     for post_path in post_paths {
         match post_path {
-            Ok(post_dir_entry) => {
-                let parsed_post = parse_post(post_dir_entry);
-                match parsed_post {
-                    Ok(post) => {
-                        parsed_posts.push(post);
-                    }
-                    Err(error_message) => {
-                        println!("There was an error with the post {}", error_message);
-                    }
-                }
-            }
+            Ok(post_dir_entry) => post_dir_entries.push(post_dir_entry),
             Err(error_message) => {
-                println!("There was an error with the post: {}", error_message);
+                eprintln!("There was an error with the post: {}", error_message);
             }
         }
     }
+    // read_dir order is platform dependent, keep the output deterministic
+    post_dir_entries.sort_by_key(|entry| entry.path());
 
-    return parsed_posts;
+    // Parse on the thread pool, collect keeps the sorted order
+    let parsed_posts: Vec<Result<Post, String>> = post_dir_entries
+        .into_par_iter()
+        .map(|post_path| parse_post(post_path, languages))
+        .collect();
+
+    parsed_posts
+        .into_iter()
+        .filter_map(|parsed_post| match parsed_post {
+            Ok(post) => Some(post),
+            Err(error_message) => {
+                eprintln!("There was an error with the post {}", error_message);
+                None
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -196,6 +320,10 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    fn languages() -> Vec<String> {
+        vec!["en".to_string()]
+    }
+
     // Helper function to create a temporary markdown file
     fn create_test_markdown_file(temp_dir: &TempDir, content: &str) -> DirEntry {
         let file_path = temp_dir.path().join("test-post.md");
@@ -229,36 +357,26 @@ mod tests {
         );
         assert_eq!(
             get_permalink_from_title("Survey #9 (2021)"),
-            "survey-9-(2021)"
+            "survey-9-2021"
         );
         assert_eq!(
             get_permalink_from_title("Title: With Colon"),
             "title-with-colon"
         );
-    }
-
-    #[test]
-    fn test_parse_html() {
-        let markdown = String::from("# Test\n\nThis is a **test**.");
-        let html = parse_html(&markdown);
-        assert!(html.contains("<h1>"));
-        assert!(html.contains("Test"));
-        assert!(html.contains("<strong>test</strong>"));
+        assert_eq!(
+            get_permalink_from_title("¿Qué pasa? Ideas & notas"),
+            "que-pasa-ideas-and-notas"
+        );
     }
 
     #[test]
     fn test_parse_frontmatter_data() {
-        let yaml = Node::Yaml(markdown::mdast::Yaml {
-            value: String::from(
-                r#"title: Test Post
+        let yaml = r#"title: Test Post
 date: 2024-01-01
 description: Test description
-keywords: test,keywords"#,
-            ),
-            position: None,
-        });
+keywords: test,keywords"#;
 
-        let result = parse_frontmatter_data(yaml).unwrap();
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
 
         assert_eq!(result.title, "Test Post");
         assert_eq!(result.date, "2024-01-01");
@@ -272,16 +390,11 @@ keywords: test,keywords"#,
 
     #[test]
     fn test_parse_frontmatter_data_with_light_theme() {
-        let yaml = Node::Yaml(markdown::mdast::Yaml {
-            value: String::from(
-                r#"title: Light Theme Post
+        let yaml = r#"title: Light Theme Post
 date: 2024-01-01
-lightTheme: true"#,
-            ),
-            position: None,
-        });
+lightTheme: true"#;
 
-        let result = parse_frontmatter_data(yaml).unwrap();
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
 
         assert!(result.light_theme);
         assert_eq!(result.theme_class(), "light-theme");
@@ -289,16 +402,11 @@ lightTheme: true"#,
 
     #[test]
     fn test_parse_frontmatter_data_with_draft() {
-        let yaml = Node::Yaml(markdown::mdast::Yaml {
-            value: String::from(
-                r#"title: Draft Post
+        let yaml = r#"title: Draft Post
 date: 2024-01-01
-publish: draft"#,
-            ),
-            position: None,
-        });
+publish: draft"#;
 
-        let result = parse_frontmatter_data(yaml).unwrap();
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
 
         assert_eq!(result.title, "Draft Post");
         assert_eq!(result.publish, Some("draft".to_string()));
@@ -307,45 +415,134 @@ publish: draft"#,
 
     #[test]
     fn test_parse_frontmatter_data_with_published() {
-        let yaml = Node::Yaml(markdown::mdast::Yaml {
-            value: String::from(
-                r#"title: Published Post
+        let yaml = r#"title: Published Post
 date: 2024-01-01
-publish: published"#,
-            ),
-            position: None,
-        });
+publish: published"#;
 
-        let result = parse_frontmatter_data(yaml).unwrap();
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
 
         assert_eq!(result.publish, Some("published".to_string()));
         assert!(!result.is_draft());
     }
 
+    #[test]
+    fn test_publication_state() {
+        let yaml = r#"title: Expiring Post
+date: 2024-01-01
+expires: 2024-06-30"#;
+
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
+
+        assert_eq!(result.expires, Some("2024-06-30".to_string()));
+        assert_eq!(result.publication_state("2023-12-31"), PublicationState::Scheduled);
+        assert_eq!(result.publication_state("2024-01-01"), PublicationState::Published);
+        assert_eq!(result.publication_state("2024-06-30"), PublicationState::Published);
+        assert_eq!(result.publication_state("2024-07-01"), PublicationState::Expired);
+        assert!(PublicationState::Scheduled.is_listed());
+        assert!(!PublicationState::Expired.is_listed());
+        assert!(!PublicationState::Draft.is_listed());
+    }
+
+    #[test]
+    fn test_publication_state_draft_wins() {
+        let yaml = r#"title: Future Draft
+date: 2099-01-01 10:00
+publish: draft"#;
+
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
+
+        assert_eq!(result.publication_state("2024-01-01"), PublicationState::Draft);
+    }
+
     #[test]
     fn test_parse_frontmatter_data_with_custom_permalink() {
-        let yaml = Node::Yaml(markdown::mdast::Yaml {
-            value: String::from(
-                r#"title: My Post Title
+        let yaml = r#"title: My Post Title
 date: 2024-01-01
-permalink: custom-url-slug"#,
-            ),
-            position: None,
-        });
+permalink: custom-url-slug"#;
 
-        let result = parse_frontmatter_data(yaml).unwrap();
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
 
         assert_eq!(result.permalink, Some("custom-url-slug".to_string()));
     }
 
+    #[test]
+    fn test_parse_frontmatter_data_with_series() {
+        let yaml = r#"title: Part Two
+date: 2024-01-01
+series: Rust Basics
+seriesOrder: 2"#;
+
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
+
+        assert_eq!(result.series.as_deref(), Some("Rust Basics"));
+        assert_eq!(result.series_order, Some(2));
+
+        let yaml = "title: Part Two\ndate: 2024-01-01\nseriesOrder: second";
+        assert!(parse_frontmatter_data(yaml, ContentKind::Post).is_err());
+    }
+
+    #[test]
+    fn test_parse_frontmatter_data_with_aliases() {
+        let yaml = r#"title: Moved Post
+date: 2024-01-01
+aliases:
+  - /old-title/
+  - 2019/first-name"#;
+
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
+
+        assert_eq!(result.aliases, vec!["/old-title/", "2019/first-name"]);
+    }
+
+    #[test]
+    fn test_parse_frontmatter_data_with_single_alias() {
+        let yaml = r#"title: Moved Post
+date: 2024-01-01
+aliases: /old-title/"#;
+
+        let result = parse_frontmatter_data(yaml, ContentKind::Post).unwrap();
+
+        assert_eq!(result.aliases, vec!["/old-title/"]);
+    }
+
+    #[test]
+    fn test_parse_frontmatter_for_page() {
+        let markdown = r#"---
+title: About me
+permalink: about
+publish: draft
+lightTheme: true
+keywords: me, myself
+layout: wide
+image: /img/me.png
+---
+
+# About"#;
+
+        let result = content::parse(markdown, "pages/about.md", "about.md", ContentKind::Page, &languages())
+            .unwrap()
+            .frontmatter;
+
+        assert_eq!(result.title, "About me");
+        assert_eq!(result.date, "");
+        assert_eq!(result.permalink, Some("about".to_string()));
+        assert!(result.is_draft());
+        assert_eq!(result.theme_class(), "light-theme");
+        assert_eq!(result.keywords, Some("me, myself".to_string()));
+        assert_eq!(result.image, Some("/img/me.png".to_string()));
+        assert_eq!(result.template_path("./assets/templates/page.html"), "./assets/templates/wide.html");
+        assert!(content::parse(markdown, "posts/about.md", "about.md", ContentKind::Post, &languages()).is_err());
+
+        let escaping = "---\ntitle: About\nlayout: ../../etc/passwd\n---\n";
+        let error = content::parse(escaping, "pages/about.md", "about.md", ContentKind::Page, &languages()).unwrap_err();
+        assert!(error.contains("layout must be a template name"), "{}", error);
+    }
+
     #[test]
     fn test_parse_frontmatter_data_missing_required_fields() {
-        let yaml = Node::Yaml(markdown::mdast::Yaml {
-            value: String::from("description: Test description"),
-            position: None,
-        });
+        let yaml = "description: Test description";
 
-        let result = parse_frontmatter_data(yaml);
+        let result = parse_frontmatter_data(yaml, ContentKind::Post);
         assert!(result.is_err());
     }
 
@@ -364,12 +561,33 @@ keywords: test,keywords
 This is test content."#;
 
         let dir_entry = create_test_markdown_file(&temp_dir, content);
-        let result = parse_post(dir_entry).unwrap();
+        let result = parse_post(dir_entry, &languages()).unwrap();
 
-        assert_eq!(result.frontmatter.title, "Test Post");
-        assert_eq!(result.frontmatter.date, "2024-01-01");
+        assert_eq!(result.content.frontmatter.title, "Test Post");
+        assert_eq!(result.content.frontmatter.date, "2024-01-01");
         assert_eq!(result.permalink, "test-post");
-        assert!(result.html.contains("<h1>Test Content</h1>"));
+        assert!(result.content.html.contains("<h1>Test Content</h1>"));
+    }
+
+    #[test]
+    fn test_parse_post_renders_gfm() {
+        let temp_dir = TempDir::new().unwrap();
+        let content = r#"---
+title: GFM Post
+date: 2024-01-01
+---
+
+| a | b |
+| - | - |
+| 1 | 2 |
+
+~~old~~ new"#;
+
+        let dir_entry = create_test_markdown_file(&temp_dir, content);
+        let result = parse_post(dir_entry, &languages()).unwrap();
+
+        assert!(result.content.html.contains("<table>"));
+        assert!(result.content.html.contains("<del>old</del>"));
     }
 
     #[test]
@@ -384,7 +602,7 @@ permalink: custom-short-url
 # Test Content"#;
 
         let dir_entry = create_test_markdown_file(&temp_dir, content);
-        let result = parse_post(dir_entry).unwrap();
+        let result = parse_post(dir_entry, &languages()).unwrap();
 
         // Should use custom permalink instead of generating from title
         assert_eq!(result.permalink, "custom-short-url");
@@ -404,7 +622,7 @@ keywords: [test,keywords]
 # Test Content"#;
 
         let dir_entry = create_test_markdown_file(&temp_dir, content);
-        let result = parse_post(dir_entry);
+        let result = parse_post(dir_entry, &languages());
         assert!(result.is_err());
         if let Err(err) = result {
             // The error should come from trying to parse the invalid YAML structure
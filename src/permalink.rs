@@ -0,0 +1,140 @@
+use crate::parser::FrontmatterData;
+
+/// Used when `PERMALINK_PATTERN` is not set, keeping posts at `/{slug}/`
+pub const DEFAULT_POST_PATTERN: &str = "/:slug/";
+
+/// Returns the site-level permalink pattern for posts
+pub fn post_pattern() -> String {
+    dotenv::var("PERMALINK_PATTERN").unwrap_or_else(|_| DEFAULT_POST_PATTERN.to_string())
+}
+
+/// Resolves a pattern such as `/:year/:month/:slug/` into an output path
+/// below `build/`, without leading or trailing slashes.
+///
+/// Supported tokens are `:year`, `:month` and `:day` (taken from the
+/// frontmatter `date`) and `:slug` (the custom permalink or the title slug).
+pub fn resolve(pattern: &str, frontmatter: &FrontmatterData, slug: &str) -> Result<String, String> {
+    let mut segments = Vec::new();
+
+    for segment in pattern.split('/').filter(|segment| !segment.is_empty()) {
+        let resolved = match segment {
+            ":year" => date_part(&frontmatter.date, 0, 4)?,
+            ":month" => date_part(&frontmatter.date, 5, 7)?,
+            ":day" => date_part(&frontmatter.date, 8, 10)?,
+            ":slug" => slug,
+            token if token.starts_with(':') => {
+                return Err(format!("Unknown permalink token {} in {}", token, pattern));
+            }
+            literal => literal,
+        };
+        segments.push(resolved.trim_matches('/'));
+    }
+
+    Ok(segments.join("/"))
+}
+
+/// Returns the absolute URL for an output path, e.g. `{host}/2024/03/slug/`.
+/// An empty path is the homepage.
+pub fn url(host: &str, path: &str) -> String {
+    if path.is_empty() {
+        format!("{}/", host)
+    } else {
+        format!("{}/{}/", host, path)
+    }
+}
+
+/// Returns the absolute URL of an image or other file, leaving full URLs untouched
+pub fn file_url(host: &str, path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        path.to_string()
+    } else {
+        format!("{}/{}", host, path.trim_start_matches('/'))
+    }
+}
+
+/// Checks a path from frontmatter or a plugin before anything is written
+/// there, returning it without surrounding slashes. A leading `/` is the
+/// site root, but `..` and `.` segments, backslashes, drive letters and
+/// `//host` paths are rejected so that nothing ends up outside `build/`.
+pub fn output_path(path: &str) -> Result<String, String> {
+    if path.contains('\\') {
+        return Err("backslashes are not allowed in paths".to_string());
+    }
+    let drive_letter = path.as_bytes().get(1) == Some(&b':') && path.starts_with(|c: char| c.is_ascii_alphabetic());
+    if path.starts_with("//") || path.contains("://") || drive_letter {
+        return Err("paths must be relative to the site root".to_string());
+    }
+    let trimmed = path.trim_matches('/');
+    if trimmed.split('/').any(|segment| segment == ".." || segment == ".") {
+        return Err("`..` and `.` segments are not allowed in paths".to_string());
+    }
+    Ok(trimmed.to_string())
+}
+
+fn date_part(date: &str, start: usize, end: usize) -> Result<&str, String> {
+    date.get(start..end)
+        .filter(|part| part.chars().all(|c| c.is_ascii_digit()))
+        .ok_or_else(|| format!("Date {} is not in YYYY-MM-DD format", date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frontmatter(date: &str) -> FrontmatterData {
+        FrontmatterData {
+            aliases: Vec::new(),
+            date: date.to_string(),
+            description: None,
+            expires: None,
+            image: None,
+            keywords: None,
+            lang: None,
+            layout: None,
+            light_theme: false,
+            math: false,
+            permalink: None,
+            publish: None,
+            series: None,
+            series_order: None,
+            tags: Vec::new(),
+            translation_key: None,
+            title: "Title".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let data = frontmatter("2024-03-09");
+
+        assert_eq!(resolve(DEFAULT_POST_PATTERN, &data, "hello").unwrap(), "hello");
+        assert_eq!(resolve("/:year/:month/:slug/", &data, "hello").unwrap(), "2024/03/hello");
+        assert_eq!(resolve("blog/:year/:month/:day/:slug", &data, "hello").unwrap(), "blog/2024/03/09/hello");
+        assert_eq!(resolve("/blog/:slug/", &data, "nested/custom").unwrap(), "blog/nested/custom");
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        assert!(resolve("/:category/:slug/", &frontmatter("2024-03-09"), "hello").is_err());
+        assert!(resolve("/:year/:slug/", &frontmatter("March 2024"), "hello").is_err());
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(url("https://example.com", ""), "https://example.com/");
+        assert_eq!(url("https://example.com", "2024/03/hello"), "https://example.com/2024/03/hello/");
+        assert_eq!(file_url("https://example.com", "/img/a.png"), "https://example.com/img/a.png");
+        assert_eq!(file_url("https://example.com", "https://cdn.test/a.png"), "https://cdn.test/a.png");
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(output_path("/old-title/").unwrap(), "old-title");
+        assert_eq!(output_path("2019/old.html").unwrap(), "2019/old.html");
+        assert!(output_path("../../etc/x").is_err());
+        assert!(output_path("/blog/./../x/").is_err());
+        assert!(output_path("..\\x").is_err());
+        assert!(output_path("//evil.test/x").is_err());
+        assert!(output_path("C:/Windows/x").is_err());
+    }
+}
@@ -1,13 +1,70 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+use serde_json::{Map, Value};
+use crate::content::Content;
 use crate::parser::Post;
+use crate::slug;
+
+/// Output paths generated by the build itself that content must not use
+pub const RESERVED_PATHS: [&str; 4] = ["", "search", "feed.xml", "sitemap.xml"];
+
+/// Capabilities of the plugins that load content, `on_content_loaded` is
+/// called once the last of them has run
+const CONTENT_CAPABILITIES: [&str; 3] = ["posts", "pages", "data"];
 
 /// Represents a static page (like about, contact, etc.)
 #[derive(Debug)]
 pub struct Page {
-    pub title: String,
-    pub description: Option<String>,
+    pub content: Content,
+    /// Path derived from the file's location below `pages/`, e.g. "docs/setup".
+    /// The section hierarchy is built from it.
     pub slug: String,
-    pub html: String,
+    /// Output path below `build/`: the custom `permalink` or the slug
+    pub permalink: String,
+    /// Slug of the closest section page above this one, e.g. "docs" for "docs/setup"
+    pub parent: Option<String>,
+}
+
+/// Listed posts of one language sharing a `series` frontmatter value
+#[derive(Debug)]
+pub struct Series {
+    pub name: String,
+    /// Language of the posts in the series
+    pub lang: String,
+    /// Output path of the series index page, e.g. "series/rust-basics" or "es/series/rust-basics"
+    pub permalink: String,
+    /// Indexes in `Site::posts`, in reading order
+    pub posts: Vec<usize>,
+}
+
+/// A date-based listing of posts: every post, one year or one month
+#[derive(Debug)]
+pub struct Archive {
+    /// "Archive", "2024" or "March 2024", with the month named in `lang`
+    pub title: String,
+    /// Language of the posts in the listing
+    pub lang: String,
+    /// Output path of the first page of the listing, e.g. "2024/03"
+    pub permalink: String,
+    /// Indexes in `Site::posts`, newest first
+    pub posts: Vec<usize>,
+    /// Indexes in `Site::archives` of the years or months inside this archive
+    pub children: Vec<usize>,
+    /// Number of pages the listing is split into
+    pub page_count: usize,
+}
+
+/// Values of a template's placeholders, e.g. `("{post_title}", title)`
+pub type Placeholders = Vec<(&'static str, String)>;
+
+/// An HTML page waiting to be rendered
+#[derive(Debug)]
+struct HtmlPage {
+    /// Path below `build/`
+    path: String,
+    template: String,
+    placeholders: Placeholders,
 }
 
 /// Represents the site's metadata and content during the build process
@@ -15,7 +72,20 @@ pub struct Page {
 pub struct Site {
     pub posts: Vec<Post>,
     pub pages: Vec<Page>,
-    pub metadata: HashMap<String, String>,
+    pub series: Vec<Series>,
+    /// Date-based listings, filled in by `ArchivePlugin`
+    pub archives: Vec<Archive>,
+    /// Structured site data, loaded from `data/` and extended by scripts and external plugins
+    pub metadata: Map<String, Value>,
+    /// Output path of every static file by its path in `public/`, e.g.
+    /// "css/app.css" to "css/app.3f9a1c0d.css" when fingerprinted
+    pub assets: BTreeMap<String, String>,
+    /// Output paths already taken, mapped to the file that claimed them
+    claimed_paths: HashMap<String, String>,
+    /// HTML pages waiting to go through the template and page hooks
+    html_pages: Vec<HtmlPage>,
+    /// Every file of the built site by path below `build/`, written to disk at the end
+    output: BTreeMap<String, Vec<u8>>,
 }
 
 impl Site {
@@ -23,38 +93,480 @@ impl Site {
         Site {
             posts: Vec::new(),
             pages: Vec::new(),
-            metadata: HashMap::new(),
+            series: Vec::new(),
+            archives: Vec::new(),
+            metadata: Map::new(),
+            assets: BTreeMap::new(),
+            claimed_paths: RESERVED_PATHS
+                .iter()
+                .map(|path| (path.to_string(), "a reserved path".to_string()))
+                .collect(),
+            html_pages: Vec::new(),
+            output: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a file to the output tree at `path` below `build/`, replacing any
+    /// file added there before
+    pub fn add_file(&mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) {
+        self.output.insert(path.into(), contents.into());
+    }
+
+    pub fn output(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.output
+    }
+
+    /// Queues an HTML file for `build/<path>`. The pipeline passes `template`
+    /// through every plugin's `on_render_template` hook, fills in the
+    /// placeholders and passes the page through every `on_render_page` hook
+    /// before adding it to the output.
+    pub fn add_html_page(&mut self, path: impl Into<String>, template: String, placeholders: Placeholders) {
+        self.html_pages.push(HtmlPage {
+            path: path.into(),
+            template,
+            placeholders,
+        });
+    }
+
+    /// Returns the pages that belong in the sitemap
+    pub fn listed_pages(&self) -> impl Iterator<Item = &Page> {
+        self.pages.iter().filter(|page| page.content.state.is_listed())
+    }
+
+    /// Registers `path` as an output of `source`, failing if another post,
+    /// page or reserved path already uses it
+    pub fn claim_path(&mut self, path: &str, source: &str) -> Result<(), String> {
+        let key = slug::normalize_path(path);
+        if let Some(owner) = self.claimed_paths.get(&key) {
+            return Err(format!(
+                "Permalink collision: /{}/ from {} is already used by {}",
+                key, source, owner
+            ));
+        }
+        self.claimed_paths.insert(key, source.to_string());
+        Ok(())
+    }
+
+    /// Returns the file or listing that claimed the output directory `path`
+    pub fn path_owner(&self, path: &str) -> Option<&str> {
+        self.claimed_paths.get(&slug::normalize_path(path)).map(String::as_str)
+    }
+
+    /// Returns the markdown file the HTML file at `page_path` was built from,
+    /// or `page_path` itself for generated pages like the homepage
+    pub fn source_file(&self, page_path: &str) -> String {
+        let directory = page_path.strip_suffix("index.html").unwrap_or(page_path);
+        match self.path_owner(directory) {
+            Some(owner) if owner.ends_with(".md") => owner.to_string(),
+            _ => page_path.to_string(),
+        }
+    }
+
+    /// Returns the posts that belong in listings, feeds and the sitemap
+    pub fn listed_posts(&self) -> impl Iterator<Item = &Post> {
+        self.posts.iter().filter(|post| post.content.state.is_listed())
+    }
+
+    /// Returns the listed posts written in `lang`
+    pub fn listed_posts_in<'a>(&'a self, lang: &'a str) -> impl Iterator<Item = &'a Post> {
+        self.listed_posts().filter(move |post| post.content.lang == lang)
+    }
+
+    /// Returns the language and output path of `post` and its listed translations,
+    /// in the order of `LANGUAGES`
+    pub fn post_translations<'a>(&'a self, post: &'a Post, languages: &[String]) -> Vec<(&'a str, &'a str)> {
+        let translations = self
+            .listed_posts()
+            .filter(|other| other.content.translation_key == post.content.translation_key && other.content.lang != post.content.lang)
+            .map(|other| (other.content.lang.as_str(), other.permalink.as_str()));
+        Self::sort_translations(translations.chain([(post.content.lang.as_str(), post.permalink.as_str())]), languages)
+    }
+
+    /// Returns the language and output path of `page` and its listed translations,
+    /// in the order of `LANGUAGES`
+    pub fn page_translations<'a>(&'a self, page: &'a Page, languages: &[String]) -> Vec<(&'a str, &'a str)> {
+        let translations = self
+            .listed_pages()
+            .filter(|other| other.content.translation_key == page.content.translation_key && other.content.lang != page.content.lang)
+            .map(|other| (other.content.lang.as_str(), other.permalink.as_str()));
+        Self::sort_translations(translations.chain([(page.content.lang.as_str(), page.permalink.as_str())]), languages)
+    }
+
+    fn sort_translations<'a>(
+        translations: impl Iterator<Item = (&'a str, &'a str)>,
+        languages: &[String],
+    ) -> Vec<(&'a str, &'a str)> {
+        let mut translations: Vec<(&str, &str)> = translations.collect();
+        translations.sort_by_key(|(lang, _)| languages.iter().position(|language| language == lang));
+        translations
+    }
+}
+
+/// Error returned by `Plugin::run`. It can be sent across threads, as plugins
+/// that are not parallel run on their own single-threaded pool.
+pub type PluginError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Fills in the placeholders of a template in one pass. Values are inserted
+/// as they are and never scanned again, so content can show placeholder or
+/// template syntax.
+pub fn fill_placeholders(template: &str, placeholders: &[(&str, String)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match placeholders.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                output.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                output.push('{');
+                rest = &rest[1..];
+            }
         }
     }
+    output.push_str(rest);
+    output
 }
 
 /// The core plugin trait that all plugins must implement
-pub trait Plugin {
+pub trait Plugin: Send + Sync {
     fn name(&self) -> &str;
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>>;
+    fn run(&self, site: &mut Site) -> Result<(), PluginError>;
+
+    /// Capabilities that must be provided before this plugin runs, e.g. "pages"
+    fn requires(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Capabilities this plugin makes available to the plugins that require them
+    fn provides(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Returns true if the plugin's per-post or per-page work may be spread
+    /// across the thread pool. Other plugins run on a single thread, so any
+    /// parallel iterator they use behaves sequentially.
+    fn is_parallel(&self) -> bool {
+        false
+    }
+
+    /// Called before any plugin runs
+    fn on_config(&self, _site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Called once the plugins providing posts, pages and data have run
+    fn on_content_loaded(&self, _site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Transforms the template of the HTML page at `build/<path>` before its
+    /// placeholders are filled in, for template syntax that must not apply to
+    /// the content of posts and pages
+    fn on_render_template(&self, _site: &Site, _path: &str, template: String) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(template)
+    }
+
+    /// Transforms an HTML page before it is added to the output at `build/<path>`
+    fn on_render_page(&self, _site: &Site, _path: &str, html: String) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(html)
+    }
+
+    /// Called after every plugin has run, once the output tree is complete
+    fn on_build_finished(&self, _site: &Site) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
 }
 
-/// A collection of plugins that will be run in sequence
+/// A collection of plugins, run in the order of their requirements
 pub struct PluginPipeline {
     plugins: Vec<Box<dyn Plugin>>,
+    /// Print how long each plugin took
+    verbose: bool,
 }
 
 impl PluginPipeline {
     pub fn new() -> Self {
         PluginPipeline {
             plugins: Vec::new(),
+            verbose: false,
         }
     }
 
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
     pub fn add_plugin<P: Plugin + 'static>(&mut self, plugin: P) {
         self.plugins.push(Box::new(plugin));
     }
 
+    /// Orders the plugins so that each one runs after the plugins providing
+    /// what it requires. Plugins that don't depend on each other keep the
+    /// order they were added in.
+    fn sorted_plugins(&self) -> Result<Vec<&dyn Plugin>, String> {
+        let mut dependencies = Vec::new();
+        for (index, plugin) in self.plugins.iter().enumerate() {
+            let mut plugin_dependencies = Vec::new();
+            for capability in plugin.requires() {
+                let providers: Vec<usize> = self
+                    .plugins
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, provider)| *other != index && provider.provides().contains(capability))
+                    .map(|(other, _)| other)
+                    .collect();
+                if providers.is_empty() {
+                    return Err(format!(
+                        "Plugin {} requires \"{}\", which no plugin provides",
+                        plugin.name(),
+                        capability
+                    ));
+                }
+                plugin_dependencies.extend(providers);
+            }
+            dependencies.push(plugin_dependencies);
+        }
+
+        let mut sorted: Vec<usize> = Vec::new();
+        while sorted.len() < self.plugins.len() {
+            let next = (0..self.plugins.len()).find(|index| {
+                !sorted.contains(index) && dependencies[*index].iter().all(|dependency| sorted.contains(dependency))
+            });
+            match next {
+                Some(index) => sorted.push(index),
+                None => {
+                    let remaining: Vec<&str> = (0..self.plugins.len())
+                        .filter(|index| !sorted.contains(index))
+                        .map(|index| self.plugins[index].name())
+                        .collect();
+                    return Err(format!("Plugin dependency cycle between: {}", remaining.join(", ")));
+                }
+            }
+        }
+
+        Ok(sorted.into_iter().map(|index| self.plugins[index].as_ref()).collect())
+    }
+
+    /// Renders the queued HTML pages through the template and page hooks and
+    /// adds them to the output tree
+    fn render_html_pages(plugins: &[&dyn Plugin], site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+        for page in std::mem::take(&mut site.html_pages) {
+            let mut template = page.template;
+            for plugin in plugins {
+                template = plugin.on_render_template(site, &page.path, template)?;
+            }
+            let mut html = fill_placeholders(&template, &page.placeholders);
+            for plugin in plugins {
+                html = plugin.on_render_page(site, &page.path, html)?;
+            }
+            site.add_file(page.path, html);
+        }
+        Ok(())
+    }
+
+    /// Runs every plugin and fills the site's output tree, writing it is up to the caller
     pub fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
-        for plugin in &self.plugins {
-            println!("Running plugin: {}", plugin.name());
-            plugin.run(site)?;
+        let plugins = self.sorted_plugins()?;
+        let sequential_pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
+
+        // Content is loaded after the last plugin providing it, or right away if there is none
+        let content_loaded_after = plugins.iter().rposition(|plugin| {
+            plugin.provides().iter().any(|capability| CONTENT_CAPABILITIES.contains(capability))
+        });
+
+        for plugin in &plugins {
+            plugin.on_config(site)?;
+        }
+        if content_loaded_after.is_none() {
+            for plugin in &plugins {
+                plugin.on_content_loaded(site)?;
+            }
+        }
+
+        for (index, plugin) in plugins.iter().enumerate() {
+            eprintln!("Running plugin: {}", plugin.name());
+            let started = Instant::now();
+            let result = if plugin.is_parallel() {
+                plugin.run(site)
+            } else {
+                sequential_pool.install(|| plugin.run(site))
+            };
+            result.map_err(|error| error as Box<dyn std::error::Error>)?;
+            Self::render_html_pages(&plugins, site)?;
+            if self.verbose {
+                eprintln!("Finished plugin: {} in {}ms", plugin.name(), started.elapsed().as_millis());
+            }
+
+            if content_loaded_after == Some(index) {
+                for plugin in &plugins {
+                    plugin.on_content_loaded(site)?;
+                }
+            }
+        }
+
+        for plugin in &plugins {
+            plugin.on_build_finished(site)?;
         }
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_path_detects_collisions() {
+        let mut site = Site::new();
+
+        assert!(site.claim_path("hello-world", "posts/a.md").is_ok());
+        assert!(site.claim_path("docs/setup", "pages/docs/setup.md").is_ok());
+
+        let error = site.claim_path("/Hello-World/", "posts/b.md").unwrap_err();
+        assert!(error.contains("posts/b.md"));
+        assert!(error.contains("posts/a.md"));
+    }
+
+    #[test]
+    fn test_claim_path_rejects_reserved_paths() {
+        let mut site = Site::new();
+
+        assert!(site.claim_path("search", "pages/search.md").is_err());
+        assert!(site.claim_path("feed.xml", "posts/feed.md").is_err());
+        assert!(site.claim_path("searching", "posts/searching.md").is_ok());
+    }
+
+    struct TestPlugin {
+        name: &'static str,
+        requires: Vec<&'static str>,
+        provides: Vec<&'static str>,
+    }
+
+    impl TestPlugin {
+        fn new(name: &'static str, requires: &[&'static str], provides: &[&'static str]) -> Self {
+            TestPlugin {
+                name,
+                requires: requires.to_vec(),
+                provides: provides.to_vec(),
+            }
+        }
+    }
+
+    impl Plugin for TestPlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+            Ok(())
+        }
+
+        fn requires(&self) -> &[&str] {
+            &self.requires
+        }
+
+        fn provides(&self) -> &[&str] {
+            &self.provides
+        }
+
+        fn on_render_template(&self, _site: &Site, _path: &str, template: String) -> Result<String, Box<dyn std::error::Error>> {
+            Ok(template.replace("{name}", self.name))
+        }
+
+        fn on_render_page(&self, _site: &Site, path: &str, html: String) -> Result<String, Box<dyn std::error::Error>> {
+            Ok(format!("{}<!-- {} {} -->", html, self.name, path))
+        }
+    }
+
+    fn sorted_names(pipeline: &PluginPipeline) -> Result<Vec<String>, String> {
+        pipeline
+            .sorted_plugins()
+            .map(|plugins| plugins.iter().map(|plugin| plugin.name().to_string()).collect())
+    }
+
+    #[test]
+    fn test_plugins_run_after_their_requirements() {
+        let mut pipeline = PluginPipeline::new();
+        pipeline.add_plugin(TestPlugin::new("sitemap", &["pages"], &[]));
+        pipeline.add_plugin(TestPlugin::new("build", &[], &["build"]));
+        pipeline.add_plugin(TestPlugin::new("page", &["build", "pages"], &[]));
+        pipeline.add_plugin(TestPlugin::new("pages", &[], &["pages"]));
+
+        assert_eq!(sorted_names(&pipeline).unwrap(), vec!["build", "pages", "sitemap", "page"]);
+    }
+
+    #[test]
+    fn test_plugin_cycles_and_missing_requirements_fail() {
+        let mut pipeline = PluginPipeline::new();
+        pipeline.add_plugin(TestPlugin::new("build", &[], &["build"]));
+        pipeline.add_plugin(TestPlugin::new("a", &["b"], &["a"]));
+        pipeline.add_plugin(TestPlugin::new("b", &["a"], &["b"]));
+        assert_eq!(sorted_names(&pipeline).unwrap_err(), "Plugin dependency cycle between: a, b");
+
+        let mut pipeline = PluginPipeline::new();
+        pipeline.add_plugin(TestPlugin::new("sitemap", &["pages"], &[]));
+        assert!(sorted_names(&pipeline).unwrap_err().contains("no plugin provides"));
+    }
+
+    #[test]
+    fn test_render_page_hooks_transform_html() {
+        let first = TestPlugin::new("first", &[], &[]);
+        let second = TestPlugin::new("second", &[], &[]);
+        let plugins: Vec<&dyn Plugin> = vec![&first, &second];
+
+        let mut site = Site::new();
+        site.add_html_page(
+            "docs/index.html",
+            "<h1>{name}</h1>{page_content}".to_string(),
+            vec![("{page_content}", "<p>Docs, not {name}</p>".to_string())],
+        );
+        PluginPipeline::render_html_pages(&plugins, &mut site).unwrap();
+
+        // Template hooks only see the template, not the content filled in after them
+        assert_eq!(
+            site.output()["docs/index.html"],
+            b"<h1>first</h1><p>Docs, not {name}</p><!-- first docs/index.html --><!-- second docs/index.html -->"
+        );
+        assert!(site.html_pages.is_empty());
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let placeholders = [
+            ("{post_title}", "Hello {post_url}".to_string()),
+            ("{post_url}", "https://example.com/hello/".to_string()),
+        ];
+
+        assert_eq!(
+            fill_placeholders("<a href=\"{post_url}\">{post_title}</a>{unknown} { x }", &placeholders),
+            "<a href=\"https://example.com/hello/\">Hello {post_url}</a>{unknown} { x }"
+        );
+    }
+
+    struct FailingPlugin;
+
+    impl Plugin for FailingPlugin {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+            Err(std::io::Error::new(std::io::ErrorKind::NotFound, "missing template").into())
+        }
+    }
+
+    #[test]
+    fn test_run_keeps_plugin_errors() {
+        let mut pipeline = PluginPipeline::new();
+        pipeline.add_plugin(FailingPlugin);
+
+        let error = pipeline.run(&mut Site::new()).unwrap_err();
+
+        let io_error = error.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(io_error.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "missing template");
+    }
+}
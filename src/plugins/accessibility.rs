@@ -0,0 +1,236 @@
+use crate::assets;
+use crate::html::{self, Token};
+use crate::plugin::{Plugin, PluginError, Site};
+
+/// A problem found on one page
+#[derive(Debug, PartialEq)]
+struct Finding {
+    /// Name of the rule, e.g. "img-alt"
+    rule: &'static str,
+    message: String,
+}
+
+impl Finding {
+    fn new(rule: &'static str, message: String) -> Self {
+        Finding { rule, message }
+    }
+}
+
+/// An element whose text is collected until it closes
+struct OpenElement {
+    name: String,
+    /// `href` of links, to name them in findings
+    href: Option<String>,
+    /// Text content plus the alt text of images inside
+    text: String,
+}
+
+/// Audits every rendered HTML page once the build is done: images without
+/// `alt`, skipped heading levels, links without text, `<html>` without
+/// `lang` and tables without header cells. Fails the build when there are
+/// more findings than `ACCESSIBILITY_MAX_ISSUES`.
+pub struct AccessibilityPlugin {
+    max_issues: usize,
+}
+
+impl AccessibilityPlugin {
+    pub fn new(max_issues: usize) -> Self {
+        AccessibilityPlugin { max_issues }
+    }
+
+    /// Returns the plugin if `ACCESSIBILITY_AUDIT` is turned on, with the
+    /// threshold from `ACCESSIBILITY_MAX_ISSUES` (0 when unset)
+    pub fn from_config() -> Result<Option<Self>, String> {
+        if !assets::flag("ACCESSIBILITY_AUDIT")? {
+            return Ok(None);
+        }
+        let max_issues = match dotenv::var("ACCESSIBILITY_MAX_ISSUES") {
+            Ok(count) => count
+                .trim()
+                .parse()
+                .map_err(|_| format!("ACCESSIBILITY_MAX_ISSUES must be a number, got \"{}\"", count))?,
+            Err(_) => 0,
+        };
+        Ok(Some(Self::new(max_issues)))
+    }
+
+    fn heading_level(name: &str) -> Option<u8> {
+        match name.as_bytes() {
+            [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+            _ => None,
+        }
+    }
+
+    /// Checks one HTML page, findings come in document order
+    fn audit(html: &str) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut open: Vec<OpenElement> = Vec::new();
+        // Whether each open table has a `<th>` so far
+        let mut tables: Vec<bool> = Vec::new();
+        let mut previous_level = None;
+        let mut seen_h1 = false;
+
+        for token in html::tokens(html) {
+            match token {
+                Token::Start(tag) => match tag.name.as_str() {
+                    "html" if tag.attribute("lang").is_none_or(|lang| lang.trim().is_empty()) => {
+                        findings.push(Finding::new("html-lang", "<html> has no lang attribute".to_string()));
+                    }
+                    "img" => {
+                        let src = tag.attribute("src").unwrap_or_default();
+                        match tag.attribute("alt") {
+                            Some(alt) => open.iter_mut().for_each(|element| element.text.push_str(alt)),
+                            None => findings.push(Finding::new("img-alt", format!("image {} has no alt attribute", src))),
+                        }
+                    }
+                    "a" if tag.attribute("href").is_some() => {
+                        // aria-label names the link on its own
+                        let label = tag.attribute("aria-label").unwrap_or_default();
+                        open.push(OpenElement {
+                            name: tag.name.clone(),
+                            href: tag.attribute("href").map(str::to_string),
+                            text: label.to_string(),
+                        });
+                    }
+                    "table" => tables.push(false),
+                    "th" => {
+                        if let Some(has_header) = tables.last_mut() {
+                            *has_header = true;
+                        }
+                    }
+                    name if Self::heading_level(name).is_some() => open.push(OpenElement {
+                        name: tag.name.clone(),
+                        href: None,
+                        text: String::new(),
+                    }),
+                    _ => {}
+                },
+                Token::Text(text) => open.iter_mut().for_each(|element| element.text.push_str(&text)),
+                Token::End(name) => {
+                    if name == "table" && tables.pop() == Some(false) {
+                        findings.push(Finding::new("table-headers", "table has no header cells".to_string()));
+                    }
+                    let Some(position) = open.iter().rposition(|element| element.name == name) else {
+                        continue;
+                    };
+                    let element = open.remove(position);
+                    let text = element.text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+                    if let Some(href) = element.href {
+                        if text.is_empty() {
+                            findings.push(Finding::new("link-text", format!("link to {} has no text", href)));
+                        }
+                    } else if let Some(level) = Self::heading_level(&name) {
+                        if level == 1 && seen_h1 {
+                            findings.push(Finding::new(
+                                "heading-order",
+                                format!("second <h1> \"{}\", the page already has one", text),
+                            ));
+                        } else if previous_level.is_some_and(|previous| level > previous + 1) {
+                            findings.push(Finding::new(
+                                "heading-order",
+                                format!("<{}> \"{}\" skips a level after <h{}>", name, text, previous_level.unwrap_or_default()),
+                            ));
+                        }
+                        seen_h1 |= level == 1;
+                        previous_level = Some(level);
+                    }
+                }
+            }
+        }
+        findings
+    }
+}
+
+impl Plugin for AccessibilityPlugin {
+    fn name(&self) -> &str {
+        "accessibility"
+    }
+
+    fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Runs once every page has gone through the `on_render_page` hooks
+    fn on_build_finished(&self, site: &Site) -> Result<(), Box<dyn std::error::Error>> {
+        let mut issue_count = 0;
+        let mut page_count = 0;
+        for (path, contents) in site.output().iter().filter(|(path, _)| path.ends_with(".html")) {
+            let findings = Self::audit(&String::from_utf8_lossy(contents));
+            if findings.is_empty() {
+                continue;
+            }
+            // Point posts and pages at their markdown file
+            let file = site.source_file(path);
+            if file == *path {
+                eprintln!("{}:", path);
+            } else {
+                eprintln!("{} ({}):", file, path);
+            }
+            for finding in &findings {
+                eprintln!("    {} [{}]", finding.message, finding.rule);
+            }
+            issue_count += findings.len();
+            page_count += 1;
+        }
+
+        eprintln!("Accessibility audit: {} issues in {} pages", issue_count, page_count);
+        if issue_count > self.max_issues {
+            return Err(format!(
+                "Found {} accessibility issues, more than ACCESSIBILITY_MAX_ISSUES={}",
+                issue_count, self.max_issues
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(html: &str) -> Vec<&'static str> {
+        AccessibilityPlugin::audit(html).iter().map(|finding| finding.rule).collect()
+    }
+
+    #[test]
+    fn test_audit() {
+        let html = concat!(
+            "<!doctype html><html><body><h1>Hello</h1>",
+            "<p><img src=\"/img/a.png\"><img src=\"/img/spacer.png\" alt=\"\"></p>",
+            "<h1>Intro</h1><h2>Setup</h2><h4>Details</h4>",
+            "<a href=\"/a/\"> </a><a href=\"/b/\"><img src=\"/img/b.png\" alt=\"B\"></a><a href=\"/c/\" aria-label=\"C\"></a>",
+            "<table><tr><td>1</td></tr></table><table><thead><tr><th>A</th></tr></thead></table>",
+            "</body></html>"
+        );
+
+        let findings = AccessibilityPlugin::audit(html);
+
+        let messages: Vec<&str> = findings.iter().map(|finding| finding.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "<html> has no lang attribute",
+                "image /img/a.png has no alt attribute",
+                "second <h1> \"Intro\", the page already has one",
+                "<h4> \"Details\" skips a level after <h2>",
+                "link to /a/ has no text",
+                "table has no header cells",
+            ]
+        );
+        assert!(rules("<html lang=en><h2>A</h2><h3>B</h3><h2>C</h2><a href=/><img src=/a.png alt=Home></a></html>").is_empty());
+    }
+
+    #[test]
+    fn test_threshold() {
+        let mut site = Site::new();
+        site.add_file("index.html", "<html lang=\"en\"><img src=\"/a.png\"></html>");
+        site.add_file("about/index.html", "<html><p>About</p></html>");
+        site.add_file("css/app.css", "<html>");
+
+        assert!(AccessibilityPlugin::new(2).on_build_finished(&site).is_ok());
+        let error = AccessibilityPlugin::new(1).on_build_finished(&site).unwrap_err();
+        assert_eq!(error.to_string(), "Found 2 accessibility issues, more than ACCESSIBILITY_MAX_ISSUES=1");
+    }
+}
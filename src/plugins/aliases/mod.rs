@@ -0,0 +1,148 @@
+use dotenv;
+
+use crate::permalink;
+use crate::plugin::{Plugin, PluginError, Site};
+
+// Redirect stub written at every alias path
+const REDIRECT_TEMPLATE: &str = r#"<!doctype html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Redirecting to {target_url}</title>
+    <link rel="canonical" href="{target_url}">
+    <meta name="robots" content="noindex">
+    <meta http-equiv="refresh" content="0; url={target_url}">
+</head>
+<body>
+    <p>This page has moved to <a href="{target_url}">{target_url}</a>.</p>
+</body>
+</html>
+"#;
+const TARGET_URL_PLACEHOLDER: &str = "{target_url}";
+
+// Server-side redirect files, enabled through REDIRECT_FILES
+const NETLIFY_REDIRECTS_FILE: &str = "_redirects";
+const NGINX_REDIRECTS_FILE: &str = "redirects.map";
+
+/// An old path and the output path it now lives at
+struct Redirect {
+    from: String,
+    to: String,
+}
+
+pub struct AliasesPlugin;
+
+impl AliasesPlugin {
+    pub fn new() -> Self {
+        AliasesPlugin
+    }
+
+    /// Returns the file an alias stub is written to, relative to `build/`.
+    /// Aliases such as `old/post.html` are written as is, any other alias
+    /// gets its own directory with an `index.html`.
+    fn stub_file_path(alias: &str) -> String {
+        if alias.ends_with(".html") || alias.ends_with(".htm") {
+            alias.to_string()
+        } else {
+            format!("{}/index.html", alias)
+        }
+    }
+
+    /// Returns the alias as an absolute path, e.g. `/old-title/`
+    fn absolute_path(alias: &str) -> String {
+        if alias.ends_with(".html") || alias.ends_with(".htm") {
+            format!("/{}", alias)
+        } else {
+            format!("/{}/", alias)
+        }
+    }
+
+    fn collect_redirects(site: &mut Site) -> Result<Vec<Redirect>, PluginError> {
+        let mut sources = Vec::new();
+        for post in &site.posts {
+            for alias in &post.content.frontmatter.aliases {
+                sources.push((alias.clone(), post.permalink.clone(), post.content.full_path.clone()));
+            }
+        }
+        for page in &site.pages {
+            for alias in &page.content.frontmatter.aliases {
+                sources.push((alias.clone(), page.permalink.clone(), page.content.full_path.clone()));
+            }
+        }
+
+        let mut redirects = Vec::new();
+        for (alias, to, source) in sources {
+            let from = permalink::output_path(&alias)
+                .map_err(|error| format!("{}: invalid alias \"{}\": {}", source, alias, error))?;
+            site.claim_path(&from, &source)?;
+            redirects.push(Redirect { from, to });
+        }
+        Ok(redirects)
+    }
+
+    fn netlify_redirects(redirects: &[Redirect]) -> String {
+        let mut lines = String::new();
+        for redirect in redirects {
+            lines.push_str(&format!(
+                "{} {} 301\n",
+                Self::absolute_path(&redirect.from),
+                permalink::url("", &redirect.to)
+            ));
+        }
+
+        lines
+    }
+
+    /// Returns entries for an nginx `map $uri $redirect_uri { include redirects.map; }`
+    fn nginx_map(redirects: &[Redirect]) -> String {
+        let mut lines = String::new();
+        for redirect in redirects {
+            let from = Self::absolute_path(&redirect.from);
+            let to = permalink::url("", &redirect.to);
+            lines.push_str(&format!("{} {};\n", from, to));
+            if from.ends_with('/') {
+                lines.push_str(&format!("{} {};\n", from.trim_end_matches('/'), to));
+            }
+        }
+
+        lines
+    }
+}
+
+impl Plugin for AliasesPlugin {
+    fn name(&self) -> &str {
+        "aliases"
+    }
+
+    fn requires(&self) -> &[&str] {
+        &["build", "posts", "pages"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        let host = dotenv::var("HOST").expect("HOST environment variable must be set");
+        let redirect_files = dotenv::var("REDIRECT_FILES").unwrap_or_default();
+
+        let redirects = Self::collect_redirects(site)?;
+
+        // Write a redirect stub at every old path
+        for redirect in &redirects {
+            let target_url = permalink::url(&host, &redirect.to);
+            site.add_html_page(
+                Self::stub_file_path(&redirect.from),
+                REDIRECT_TEMPLATE.to_string(),
+                vec![(TARGET_URL_PLACEHOLDER, target_url)],
+            );
+        }
+
+        // Optionally emit server-side redirect files
+        for kind in redirect_files.split(',').map(str::trim).filter(|kind| !kind.is_empty()) {
+            match kind {
+                "netlify" => site.add_file(NETLIFY_REDIRECTS_FILE, Self::netlify_redirects(&redirects)),
+                "nginx" => site.add_file(NGINX_REDIRECTS_FILE, Self::nginx_map(&redirects)),
+                unknown => return Err(format!("Unknown REDIRECT_FILES entry: {}", unknown).into()),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate};
+use dotenv;
+
+use crate::i18n::{self, Strings};
+use crate::pagination;
+use crate::parser::{self, Post};
+use crate::permalink;
+use crate::plugin::{Archive, Placeholders, Plugin, PluginError, Site};
+use crate::post_item;
+
+// Template filepaths
+const ARCHIVE_TEMPLATE_FILE_PATH: &str = "./assets/templates/archive.html";
+
+// Templates placeholders
+const ARCHIVE_TITLE_PLACEHOLDER: &str = "{archive_title}";
+const ARCHIVE_URL_PLACEHOLDER: &str = "{archive_url}";
+const ARCHIVE_POST_COUNT_PLACEHOLDER: &str = "{archive_post_count}";
+const ARCHIVE_LINKS_PLACEHOLDER: &str = "{archive_links}";
+const PAGINATION_PLACEHOLDER: &str = "{pagination}";
+const POST_ITEMS_PLACEHOLDER: &str = "{post_items}";
+const HOST_PLACEHOLDER: &str = "{host}";
+const WEBSITE_NAME: &str = "{website_name}";
+const WEBSITE_LOGO_URL: &str = "{website_logo_url}";
+const AUTHOR_NAME: &str = "{author_name}";
+
+// The listing of every post
+const ARCHIVE_TITLE: &str = "Archive";
+const ARCHIVE_PATH: &str = "archive";
+
+/// Groups listed posts by language, year and month and generates `/archive/`,
+/// `/2024/` and `/2024/03/` listing pages, below the language prefix for
+/// translations and paginated by `POSTS_PER_PAGE`
+pub struct ArchivePlugin;
+
+impl ArchivePlugin {
+    pub fn new() -> Self {
+        ArchivePlugin
+    }
+
+    /// Returns the index of the archive at `permalink`, adding it as a child
+    /// of `parent` if it doesn't exist yet
+    fn find_or_add(archives: &mut Vec<Archive>, parent: usize, title: String, permalink: String) -> usize {
+        if let Some(index) = archives.iter().position(|archive| archive.permalink == permalink) {
+            return index;
+        }
+        archives.push(Archive {
+            title,
+            lang: archives[parent].lang.clone(),
+            permalink,
+            posts: Vec::new(),
+            children: Vec::new(),
+            page_count: 1,
+        });
+        let index = archives.len() - 1;
+        archives[parent].children.push(index);
+        index
+    }
+
+    /// Builds, for every language, the archive of its listed posts followed by
+    /// each year and its months, newest first. Posts are sorted newest first.
+    /// Months are named from the `months.*` UI strings in `strings`.
+    fn collect_archives(
+        posts: &[Post],
+        per_page: Option<usize>,
+        languages: &[String],
+        strings: &HashMap<String, Strings>,
+    ) -> Vec<Archive> {
+        let mut archives = Vec::new();
+        for lang in languages {
+            let root = archives.len();
+            archives.push(Archive {
+                title: ARCHIVE_TITLE.to_string(),
+                lang: lang.clone(),
+                permalink: i18n::localize(lang, ARCHIVE_PATH, &languages[0]),
+                posts: Vec::new(),
+                children: Vec::new(),
+                page_count: 1,
+            });
+
+            let listed = posts
+                .iter()
+                .enumerate()
+                .filter(|(_, post)| post.content.state.is_listed() && &post.content.lang == lang);
+            for (index, post) in listed {
+                archives[root].posts.push(index);
+                let Ok(date) = NaiveDate::parse_from_str(parser::day_of(&post.content.frontmatter.date), "%Y-%m-%d")
+                else {
+                    continue;
+                };
+
+                let year = Self::find_or_add(
+                    &mut archives,
+                    root,
+                    date.format("%Y").to_string(),
+                    i18n::localize(lang, &date.format("%Y").to_string(), &languages[0]),
+                );
+                archives[year].posts.push(index);
+                let month_name = i18n::month_name(strings, lang, &languages[0], date.month());
+                let month = Self::find_or_add(
+                    &mut archives,
+                    year,
+                    format!("{} {}", month_name, date.format("%Y")),
+                    i18n::localize(lang, &date.format("%Y/%m").to_string(), &languages[0]),
+                );
+                archives[month].posts.push(index);
+            }
+        }
+
+        for archive in &mut archives {
+            archive.page_count = pagination::paginate(&archive.posts, per_page).len();
+        }
+        archives
+    }
+
+    /// Renders the links to the years or months inside an archive, with their post counts
+    fn render_links(archives: &[Archive], archive: &Archive, host: &str) -> String {
+        if archive.children.is_empty() {
+            return String::new();
+        }
+
+        let mut links = String::new();
+        for &index in &archive.children {
+            let child = &archives[index];
+            links.push_str(&format!(
+                r#"<li><a href="{}">{}</a> <span class="post-count">({})</span></li>"#,
+                permalink::url(host, &child.permalink),
+                child.title,
+                child.posts.len()
+            ));
+        }
+        format!(r#"<ul class="archive-links">{}</ul>"#, links)
+    }
+}
+
+impl Plugin for ArchivePlugin {
+    fn name(&self) -> &str {
+        "archive"
+    }
+
+    fn requires(&self) -> &[&str] {
+        &["build", "posts"]
+    }
+
+    fn provides(&self) -> &[&str] {
+        &["archives"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        // Get environment variables
+        let website_name = dotenv::var("WEBSITE_NAME").expect("WEBSITE_NAME environment variable must be set");
+        let author_name = dotenv::var("AUTHOR_NAME").expect("AUTHOR_NAME environment variable must be set");
+        let host = dotenv::var("HOST").expect("HOST environment variable must be set");
+        let website_logo_url = dotenv::var("WEBSITE_LOGO_URL").expect("WEBSITE_LOGO_URL environment variable must be set");
+        let per_page = pagination::per_page()?;
+
+        let languages = i18n::languages();
+        let strings = i18n::load_strings(Path::new(i18n::STRINGS_DIR), &languages)?;
+
+        let archives = Self::collect_archives(&site.posts, per_page, &languages, &strings);
+        for archive in &archives {
+            for number in 1..=archive.page_count {
+                let path = pagination::page_path(&archive.permalink, number);
+                site.claim_path(&path, &format!("archive \"{}\"", archive.title))?;
+            }
+        }
+
+        let archive_template = fs::read_to_string(ARCHIVE_TEMPLATE_FILE_PATH)?;
+        let post_item_template = fs::read_to_string(post_item::POST_ITEM_PARTIAL_FILE_PATH)?;
+
+        let mut rendered_pages = Vec::new();
+        for archive in &archives {
+            let archive_links = Self::render_links(&archives, archive, &host);
+            let pages = pagination::paginate(&archive.posts, per_page);
+
+            for (page_index, page_posts) in pages.iter().enumerate() {
+                let number = page_index + 1;
+                let mut post_items = String::new();
+                for &index in page_posts.iter() {
+                    let post = &site.posts[index];
+                    let post_item = post_item::render_post_item(&post_item_template, &host, post);
+                    post_items.push_str(&post_item);
+                }
+
+                let path = pagination::page_path(&archive.permalink, number);
+                let placeholders: Placeholders = vec![
+                    (POST_ITEMS_PLACEHOLDER, post_items),
+                    (ARCHIVE_LINKS_PLACEHOLDER, archive_links.clone()),
+                    (
+                        PAGINATION_PLACEHOLDER,
+                        pagination::render_navigation(&host, &archive.permalink, number, pages.len()),
+                    ),
+                    (ARCHIVE_TITLE_PLACEHOLDER, archive.title.clone()),
+                    (ARCHIVE_URL_PLACEHOLDER, permalink::url(&host, &path)),
+                    (ARCHIVE_POST_COUNT_PLACEHOLDER, archive.posts.len().to_string()),
+                    (HOST_PLACEHOLDER, host.clone()),
+                    (WEBSITE_NAME, website_name.clone()),
+                    (WEBSITE_LOGO_URL, website_logo_url.clone()),
+                    (AUTHOR_NAME, author_name.clone()),
+                ];
+                rendered_pages.push((format!("{}/index.html", path), placeholders));
+            }
+        }
+
+        for (path, placeholders) in rendered_pages {
+            site.add_html_page(path, archive_template.clone(), placeholders);
+        }
+        site.archives = archives;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn languages() -> Vec<String> {
+        vec!["en".to_string(), "es".to_string()]
+    }
+
+    fn post(date: &str, extra: &str) -> Post {
+        let markdown = format!("---\ntitle: Post\ndate: {}\n{}---\n", date, extra);
+        Post::for_test(&markdown, "posts/post.md")
+    }
+
+    #[test]
+    fn test_collect_archives() {
+        // Newest first, like PostsPlugin sorts them
+        let posts = vec![
+            post("2024-03-20", ""),
+            post("2024-03-02", ""),
+            post("2024-02-10", "publish: draft\n"),
+            post("2024-01-15", ""),
+            post("2023-12-31", ""),
+            post("2023-12-24", "lang: es\n"),
+        ];
+        let strings = HashMap::from([(
+            "es".to_string(),
+            Strings::from([("months.december".to_string(), "diciembre".to_string())]),
+        )]);
+
+        let archives = ArchivePlugin::collect_archives(&posts, Some(1), &languages(), &strings);
+
+        let summary: Vec<(&str, &str, Vec<usize>, usize)> = archives
+            .iter()
+            .map(|archive| (archive.title.as_str(), archive.permalink.as_str(), archive.posts.clone(), archive.page_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Archive", "archive", vec![0, 1, 3, 4], 4),
+                ("2024", "2024", vec![0, 1, 3], 3),
+                ("March 2024", "2024/03", vec![0, 1], 2),
+                ("January 2024", "2024/01", vec![3], 1),
+                ("2023", "2023", vec![4], 1),
+                ("December 2023", "2023/12", vec![4], 1),
+                ("Archive", "es/archive", vec![5], 1),
+                ("2023", "es/2023", vec![5], 1),
+                ("diciembre 2023", "es/2023/12", vec![5], 1),
+            ]
+        );
+        assert!(archives[6..].iter().all(|archive| archive.lang == "es"));
+        assert_eq!(archives[6].children, vec![7]);
+        assert_eq!(archives[0].children, vec![1, 4]);
+        assert_eq!(archives[1].children, vec![2, 3]);
+        assert!(archives[2].children.is_empty());
+    }
+}
@@ -1,60 +1,87 @@
-use crate::plugin::{Plugin, Site};
-use std::{env, fs, path::Path};
+use crate::assets::{self, AssetOptions};
+use crate::builder::BuildOptions;
+use crate::plugin::{Plugin, PluginError, Site};
+use std::{fs, path::Path};
 
-pub struct BuildPlugin;
+const PUBLIC_DIR: &str = "public";
+// Every .scss file here is compiled to css/, except partials starting with _
+const SCSS_DIR: &str = "assets/scss";
+const CSS_DIR: &str = "css";
+// Maps every static file to its output path, written when assets are fingerprinted
+const ASSET_MANIFEST_PATH: &str = "asset-manifest.json";
 
-impl BuildPlugin {
-    pub fn new() -> Self {
-        BuildPlugin
-    }
-
-    fn get_build_dir() -> String {
-        let binding = env::current_dir().unwrap().into_os_string();
-        let current_path = binding.to_str().unwrap();
-        format!("{}/build/", current_path)
-    }
+/// Starts the output tree with the static files from `public/` and the
+/// stylesheets compiled from `assets/scss/`, minified and fingerprinted if
+/// configured, and resolves `{asset "path"}` helpers
+pub struct BuildPlugin {
+    options: BuildOptions,
+}
 
-    fn clean_build_dir(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let build_path = Self::get_build_dir();
-        if Path::new(&build_path).exists() {
-            fs::remove_dir_all(build_path)?;
-        }
-        Ok(())
+impl BuildPlugin {
+    pub fn new(options: BuildOptions) -> Self {
+        BuildPlugin { options }
     }
 
-    fn create_build_dir(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let build_path = Self::get_build_dir();
-        fs::create_dir_all(build_path)?;
+    /// Adds a file at `path` below `build/`, recording where it ends up
+    fn add_asset(
+        site: &mut Site,
+        path: String,
+        contents: Vec<u8>,
+        options: AssetOptions,
+    ) -> Result<(), PluginError> {
+        let (output_path, contents) = assets::process(&path, contents, options)?;
+        site.assets.insert(path, output_path.clone());
+        site.add_file(output_path, contents);
         Ok(())
     }
 
-    fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        if !dst.exists() {
-            fs::create_dir_all(dst)?;
-        }
-
+    fn add_dir_recursive(
+        site: &mut Site,
+        src: &Path,
+        prefix: &str,
+        options: AssetOptions,
+    ) -> Result<(), PluginError> {
         for entry in fs::read_dir(src)? {
             let entry = entry?;
             let src_path = entry.path();
-            let dst_path = dst.join(entry.file_name());
+            let source_path = format!("{}{}", prefix, entry.file_name().to_string_lossy());
 
             if src_path.is_dir() {
-                Self::copy_dir_recursive(&src_path, &dst_path)?;
+                Self::add_dir_recursive(site, &src_path, &format!("{}/", source_path), options)?;
             } else {
-                fs::copy(&src_path, &dst_path)?;
+                Self::add_asset(site, source_path, fs::read(&src_path)?, options)?;
             }
         }
 
         Ok(())
     }
 
-    fn copy_assets(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let build_dir = Self::get_build_dir();
-        let public_dir = Path::new("public");
-        let build_path = Path::new(&build_dir);
+    /// Compiles every SCSS entry point in `assets/scss/` to `css/<name>.css`
+    fn add_stylesheets(&self, site: &mut Site, options: AssetOptions) -> Result<(), PluginError> {
+        let scss_dir = Path::new(SCSS_DIR);
+        if !scss_dir.is_dir() {
+            return Ok(());
+        }
 
-        Self::copy_dir_recursive(public_dir, build_path)?;
+        let mut entry_points: Vec<_> = fs::read_dir(scss_dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        entry_points.retain(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.is_file() && name.ends_with(".scss") && !name.starts_with('_')
+        });
+        entry_points.sort();
 
+        for entry_point in entry_points {
+            let name = entry_point.file_stem().unwrap_or_default().to_string_lossy();
+            let path = format!("{}/{}.css", CSS_DIR, name);
+            if site.assets.contains_key(&path) {
+                return Err(format!("{} is both in {}/ and compiled from {}", path, PUBLIC_DIR, entry_point.display()).into());
+            }
+
+            let css = assets::compile_scss(&entry_point, scss_dir, self.options.dev)?;
+            Self::add_asset(site, path, css.into_bytes(), options)?;
+        }
         Ok(())
     }
 }
@@ -64,10 +91,30 @@ impl Plugin for BuildPlugin {
         "build"
     }
 
-    fn run(&self, _site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
-        self.clean_build_dir()?;
-        self.create_build_dir()?;
-        self.copy_assets()?;
+    fn provides(&self) -> &[&str] {
+        &["build"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        let mut options = AssetOptions::from_env()?;
+        // Development builds keep CSS and JS readable
+        if self.options.dev {
+            options.minify = false;
+        }
+        Self::add_dir_recursive(site, Path::new(PUBLIC_DIR), "", options)?;
+        self.add_stylesheets(site, options)?;
+
+        if options.fingerprint {
+            let manifest = serde_json::to_string_pretty(&site.assets)?;
+            site.add_file(ASSET_MANIFEST_PATH, manifest);
+        }
         Ok(())
     }
-} 
\ No newline at end of file
+
+    fn on_render_template(&self, site: &Site, path: &str, template: String) -> Result<String, Box<dyn std::error::Error>> {
+        if !template.contains(assets::ASSET_HELPER_START) {
+            return Ok(template);
+        }
+        Ok(assets::resolve_helpers(&template, &site.assets).map_err(|error| format!("{}: {}", path, error))?)
+    }
+}
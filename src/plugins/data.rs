@@ -0,0 +1,309 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::plugin::{Plugin, PluginError, Site};
+use crate::render;
+
+const DATA_DIR: &str = "data";
+// Loops over data in templates
+const FOR_START: &str = "{% for ";
+const FOR_END: &str = "{% endfor %}";
+const TAG_END: &str = "%}";
+// The name data files are available under
+const DATA_VARIABLE: &str = "data";
+
+/// Loads `data/*.{yaml,yml,json,toml,csv}` into `Site::metadata`, keyed by
+/// file name, and expands `{% for item in data.name %}` loops in page templates
+pub struct DataPlugin;
+
+impl DataPlugin {
+    pub fn new() -> Self {
+        DataPlugin
+    }
+
+    fn parse_csv(source: &str) -> Result<Value, String> {
+        let mut reader = csv::Reader::from_reader(source.as_bytes());
+        let headers = reader.headers().map_err(|error| error.to_string())?.clone();
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|error| error.to_string())?;
+            let row: Map<String, Value> = headers
+                .iter()
+                .zip(record.iter())
+                .map(|(header, field)| (header.to_string(), Value::String(field.to_string())))
+                .collect();
+            rows.push(Value::Object(row));
+        }
+        Ok(Value::Array(rows))
+    }
+
+    fn parse_data_file(path: &Path) -> Result<Option<Value>, String> {
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        if !["yaml", "yml", "json", "toml", "csv"].contains(&extension) {
+            return Ok(None);
+        }
+
+        let source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let value = match extension {
+            "yaml" | "yml" => serde_yaml::from_str(&source).map_err(|error| error.to_string())?,
+            "json" => serde_json::from_str(&source).map_err(|error| error.to_string())?,
+            "toml" => toml::from_str(&source).map_err(|error| error.to_string())?,
+            _ => Self::parse_csv(&source)?,
+        };
+        Ok(Some(value))
+    }
+
+    fn load_data(dir: &Path) -> Result<Map<String, Value>, String> {
+        let mut data = Map::new();
+        if !dir.is_dir() {
+            return Ok(data);
+        }
+
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map_err(|error| format!("Failed to read {}: {}", dir.display(), error))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Some(value) =
+                Self::parse_data_file(&path).map_err(|error| format!("Failed to load {}: {}", path.display(), error))?
+            else {
+                continue;
+            };
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            if data.insert(name.clone(), value).is_some() {
+                return Err(format!("More than one data file is named {}", name));
+            }
+        }
+        Ok(data)
+    }
+
+    /// Follows a dotted path such as `talk.speaker.name` or `data.talks.0`.
+    /// `data.<file>` resolves to a loaded data file, other names to loop variables.
+    fn lookup<'a>(data: &'a Map<String, Value>, scope: &[(&str, &'a Value)], path: &str) -> Result<&'a Value, String> {
+        let mut segments = path.split('.');
+        let name = segments.next().unwrap_or_default();
+        let (mut value, mut resolved) = match scope.iter().rev().find(|(bound, _)| *bound == name) {
+            Some((_, value)) => (*value, name.to_string()),
+            None if name == DATA_VARIABLE => {
+                let file = segments
+                    .next()
+                    .ok_or_else(|| format!("{{{}}} needs the name of a data file, such as {{data.projects}}", path))?;
+                let value = data.get(file).ok_or_else(|| format!("Unknown data file {} in {{{}}}", file, path))?;
+                (value, format!("{}.{}", name, file))
+            }
+            None => return Err(format!("Unknown variable {} in {{{}}}", name, path)),
+        };
+
+        for segment in segments {
+            value = match value {
+                Value::Object(map) => map.get(segment),
+                Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+                _ => None,
+            }
+            .ok_or_else(|| format!("Unknown variable {{{}}}, {} has no {}", path, resolved, segment))?;
+            resolved = format!("{}.{}", resolved, segment);
+        }
+        Ok(value)
+    }
+
+    fn is_variable_path(text: &str) -> bool {
+        !text.is_empty()
+            && text
+                .chars()
+                .all(|character| character.is_alphanumeric() || matches!(character, '_' | '-' | '.'))
+    }
+
+    /// Replaces `{name.path}` with the escaped value when `name` is `data` or
+    /// a loop variable, anything else between braces is left alone
+    fn substitute(text: &str, data: &Map<String, Value>, scope: &[(&str, &Value)]) -> Result<String, String> {
+        let mut output = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let variable = rest[1..].find('}').map(|end| &rest[1..end + 1]);
+            let bound = variable.filter(|path| {
+                let name = path.split('.').next().unwrap_or_default();
+                Self::is_variable_path(path)
+                    && (name == DATA_VARIABLE || scope.iter().any(|(bound, _)| *bound == name))
+            });
+            match bound {
+                Some(path) => {
+                    let rendered = match Self::lookup(data, scope, path)? {
+                        Value::Null => String::new(),
+                        Value::String(text) => render::escape(text),
+                        Value::Array(_) => {
+                            return Err(format!("{{{}}} is a list, loop over it with {{% for item in {} %}}", path, path))
+                        }
+                        Value::Object(map) => {
+                            let fields: Vec<&str> = map.keys().map(String::as_str).collect();
+                            return Err(format!("{{{}}} is a map, print one of its fields instead: {}", path, fields.join(", ")));
+                        }
+                        value => value.to_string(),
+                    };
+                    output.push_str(&rendered);
+                    rest = &rest[path.len() + 2..];
+                }
+                None => {
+                    output.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Returns the position of the `{% endfor %}` closing the loop `text` starts in
+    fn find_loop_end(text: &str) -> Option<usize> {
+        let mut depth = 0;
+        let mut position = 0;
+        loop {
+            let next_start = text[position..].find(FOR_START).map(|index| position + index);
+            let next_end = text[position..].find(FOR_END).map(|index| position + index)?;
+            match next_start {
+                Some(start) if start < next_end => {
+                    depth += 1;
+                    position = start + FOR_START.len();
+                }
+                _ if depth == 0 => return Some(next_end),
+                _ => {
+                    depth -= 1;
+                    position = next_end + FOR_END.len();
+                }
+            }
+        }
+    }
+
+    /// Expands `{% for item in list %}...{% endfor %}` loops and variables.
+    /// Unknown variables and data files are errors, null values render as nothing.
+    fn render(template: &str, data: &Map<String, Value>, scope: &[(&str, &Value)]) -> Result<String, String> {
+        let mut output = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(FOR_START) {
+            output.push_str(&Self::substitute(&rest[..start], data, scope)?);
+
+            let header_start = start + FOR_START.len();
+            let header_length = rest[header_start..].find(TAG_END).ok_or("Unclosed for tag")?;
+            let header = &rest[header_start..header_start + header_length];
+            let (name, path) = header
+                .split_once(" in ")
+                .map(|(name, path)| (name.trim(), path.trim()))
+                .ok_or_else(|| format!("Invalid loop: {{% for {}%}}", header))?;
+
+            let body_start = header_start + header_length + TAG_END.len();
+            let body_length = Self::find_loop_end(&rest[body_start..])
+                .ok_or_else(|| format!("Loop over {} has no {}", path, FOR_END))?;
+            let body = &rest[body_start..body_start + body_length];
+
+            match Self::lookup(data, scope, path)? {
+                Value::Array(items) => {
+                    for item in items {
+                        let mut item_scope = scope.to_vec();
+                        item_scope.push((name, item));
+                        output.push_str(&Self::render(body, data, &item_scope)?);
+                    }
+                }
+                Value::Null => {}
+                _ => return Err(format!("Can't loop over {}, it is not a list", path)),
+            }
+            rest = &rest[body_start + body_length + FOR_END.len()..];
+        }
+        output.push_str(&Self::substitute(rest, data, scope)?);
+        Ok(output)
+    }
+}
+
+impl Plugin for DataPlugin {
+    fn name(&self) -> &str {
+        "data"
+    }
+
+    fn provides(&self) -> &[&str] {
+        &["data"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        site.metadata.extend(Self::load_data(Path::new(DATA_DIR))?);
+        Ok(())
+    }
+
+    fn on_render_template(&self, site: &Site, path: &str, template: String) -> Result<String, Box<dyn std::error::Error>> {
+        if !template.contains(FOR_START) && !template.contains("{data") {
+            return Ok(template);
+        }
+
+        Ok(Self::render(&template, &site.metadata, &[]).map_err(|error| format!("{}: {}", path, error))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_load_data() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("projects.yaml"), "- name: Smith\n  stars: 42\n").unwrap();
+        fs::write(dir.path().join("owner.json"), r#"{"name": "Jane"}"#).unwrap();
+        fs::write(dir.path().join("site.toml"), "languages = [\"en\", \"es\"]\n").unwrap();
+        fs::write(dir.path().join("talks.csv"), "title,year\nRust at scale,2024\n\"Commas, quoted\",2023\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let data = DataPlugin::load_data(dir.path()).unwrap();
+
+        assert_eq!(
+            Value::Object(data),
+            json!({
+                "owner": {"name": "Jane"},
+                "projects": [{"name": "Smith", "stars": 42}],
+                "site": {"languages": ["en", "es"]},
+                "talks": [{"title": "Rust at scale", "year": "2024"}, {"title": "Commas, quoted", "year": "2023"}]
+            })
+        );
+
+        fs::write(dir.path().join("talks.yaml"), "[]").unwrap();
+        assert!(DataPlugin::load_data(dir.path()).unwrap_err().contains("talks"));
+    }
+
+    #[test]
+    fn test_render_loops_and_variables() {
+        let Value::Object(data) = json!({
+            "owner": {"name": "Jane & co", "site": null},
+            "talks": [
+                {"title": "Rust", "tags": ["a", "b"], "year": 2024},
+                {"title": "Markdown", "tags": [], "year": 2023}
+            ]
+        }) else {
+            unreachable!()
+        };
+        let template = "<h1>{data.owner.name}{data.owner.site}</h1>{% for talk in data.talks %}<li>{talk.title} {talk.year}:{% for tag in talk.tags %} {tag}{% endfor %}</li>{% endfor %}<style>p { margin: 0 }</style>{post_title}";
+
+        let html = DataPlugin::render(template, &data, &[]).unwrap();
+
+        assert_eq!(
+            html,
+            "<h1>Jane &amp; co</h1><li>Rust 2024: a b</li><li>Markdown 2023:</li><style>p { margin: 0 }</style>{post_title}"
+        );
+        assert_eq!(
+            DataPlugin::render("{% for talk in data.talks %}{talk.tags.0}{% endfor %}", &data, &[]).unwrap_err(),
+            "Unknown variable {talk.tags.0}, talk.tags has no 0"
+        );
+        assert!(DataPlugin::render("{data.missing.name}", &data, &[]).unwrap_err().contains("Unknown data file missing"));
+        assert!(DataPlugin::render("{data.owner.email}", &data, &[]).unwrap_err().contains("data.owner has no email"));
+        assert!(DataPlugin::render("{data.talks}", &data, &[]).unwrap_err().contains("{% for item in data.talks %}"));
+        assert!(DataPlugin::render("{data.owner}", &data, &[]).unwrap_err().contains("name, site"));
+        assert!(DataPlugin::render("{data}", &data, &[]).is_err());
+        assert!(DataPlugin::render("{% for x in data.owner %}{% endfor %}", &data, &[]).is_err());
+        assert!(DataPlugin::render("{% for x in data.talks %}", &data, &[]).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use dotenv;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::content::Content;
+use crate::parser::{self, FrontmatterData};
+use crate::permalink;
+use crate::plugin::{Plugin, PluginError, Site};
+
+/// What an external plugin receives on stdin, also given to scripts
+#[derive(Serialize)]
+pub struct SitePayload<'a> {
+    posts: Vec<ContentPayload<'a>>,
+    pages: Vec<ContentPayload<'a>>,
+    metadata: &'a Map<String, Value>,
+}
+
+#[derive(Serialize)]
+struct ContentPayload<'a> {
+    /// Source file, used to match updates to posts and pages
+    path: &'a str,
+    slug: &'a str,
+    permalink: &'a str,
+    frontmatter: &'a FrontmatterData,
+    html: &'a str,
+}
+
+impl<'a> ContentPayload<'a> {
+    fn new(content: &'a Content, slug: &'a str, permalink: &'a str) -> Self {
+        ContentPayload {
+            path: &content.full_path,
+            slug,
+            permalink,
+            frontmatter: &content.frontmatter,
+            html: &content.html,
+        }
+    }
+}
+
+impl<'a> SitePayload<'a> {
+    pub fn new(site: &'a Site) -> Self {
+        SitePayload {
+            posts: site
+                .posts
+                .iter()
+                .map(|post| ContentPayload::new(&post.content, &post.slug, &post.permalink))
+                .collect(),
+            pages: site
+                .pages
+                .iter()
+                .map(|page| ContentPayload::new(&page.content, &page.slug, &page.permalink))
+                .collect(),
+            metadata: &site.metadata,
+        }
+    }
+}
+
+/// What an external plugin writes to stdout, every field is optional
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct PluginResponse {
+    posts: Vec<ContentUpdate>,
+    pages: Vec<ContentUpdate>,
+    metadata: Map<String, Value>,
+    files: Vec<OutputFile>,
+}
+
+#[derive(Deserialize)]
+struct ContentUpdate {
+    path: String,
+    html: Option<String>,
+    /// Frontmatter fields to replace, the others are kept
+    frontmatter: Option<Map<String, Value>>,
+}
+
+#[derive(Deserialize)]
+struct OutputFile {
+    /// Path below `build/`
+    path: String,
+    contents: String,
+}
+
+/// Runs an executable declared in `EXTERNAL_PLUGINS` once content is loaded.
+/// The site is sent as JSON on stdin and the changes are read back from stdout.
+pub struct ExternalPlugin {
+    name: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl ExternalPlugin {
+    /// Reads the comma separated commands in `EXTERNAL_PLUGINS`, e.g.
+    /// `./scripts/cdn.py,node scripts/toc.js`
+    pub fn from_config() -> Vec<Self> {
+        dotenv::var("EXTERNAL_PLUGINS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|command| {
+                let mut parts = command.split_whitespace().map(str::to_string);
+                let program = parts.next()?;
+                Some(ExternalPlugin {
+                    name: format!("external {}", command.trim()),
+                    program,
+                    args: parts.collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Pipes `input` to the command and returns its stdout
+    fn execute(&self, input: &str) -> Result<String, String> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|error| format!("Failed to start {}: {}", self.program, error))?;
+
+        // Write on another thread so a plugin that answers while reading can't block us
+        let mut stdin = child.stdin.take().ok_or("Failed to open stdin")?;
+        let output = thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(input.as_bytes()));
+            let output = child.wait_with_output();
+            // A plugin may exit without reading everything, that is not an error
+            let _ = writer.join();
+            output
+        })
+        .map_err(|error| format!("Failed to run {}: {}", self.program, error))?;
+
+        if !output.status.success() {
+            return Err(format!("{} exited with {}", self.program, output.status));
+        }
+        String::from_utf8(output.stdout).map_err(|error| format!("{} wrote invalid UTF-8: {}", self.program, error))
+    }
+}
+
+/// Frontmatter fields that decide output paths, order, language or whether
+/// content is built at all. They are resolved before plugins run, so plugins
+/// can't change them.
+const FIXED_FRONTMATTER_FIELDS: [&str; 9] = [
+    "title",
+    "date",
+    "permalink",
+    "publish",
+    "lang",
+    "translationKey",
+    "series",
+    "seriesOrder",
+    "math",
+];
+
+impl PluginResponse {
+    fn update_content(content: &mut Content, update: ContentUpdate) -> Result<(), String> {
+        if let Some(html) = update.html {
+            content.html = html;
+        }
+        if let Some(fields) = update.frontmatter {
+            let mut frontmatter = match serde_json::to_value(&content.frontmatter) {
+                Ok(Value::Object(frontmatter)) => frontmatter,
+                _ => return Err("Failed to serialize frontmatter".to_string()),
+            };
+            // Scripts send every field back, only actual changes are rejected
+            let fixed_change = FIXED_FRONTMATTER_FIELDS
+                .iter()
+                .find(|field| fields.get(**field).is_some_and(|value| frontmatter.get(**field) != Some(value)));
+            if let Some(field) = fixed_change {
+                return Err(format!(
+                    "{}: frontmatter field \"{}\" can't be changed by plugins, it is resolved before they run",
+                    content.full_path, field
+                ));
+            }
+            frontmatter.extend(fields);
+            content.frontmatter = serde_json::from_value(Value::Object(frontmatter))
+                .map_err(|error| format!("Invalid frontmatter for {}: {}", content.full_path, error))?;
+            if let Some(layout) = &content.frontmatter.layout {
+                parser::check_layout(layout).map_err(|error| format!("{}: {}", content.full_path, error))?;
+            }
+            content.state = content.frontmatter.publication_state(&parser::today());
+        }
+        Ok(())
+    }
+
+    /// Applies the changes to posts, pages and metadata and adds the files to
+    /// the output, claiming their paths for `source`
+    pub fn apply(self, site: &mut Site, source: &str) -> Result<(), String> {
+        for update in self.posts {
+            let post = site
+                .posts
+                .iter_mut()
+                .find(|post| post.content.full_path == update.path)
+                .ok_or_else(|| format!("Unknown post {}", update.path))?;
+            Self::update_content(&mut post.content, update)?;
+        }
+        for update in self.pages {
+            let page = site
+                .pages
+                .iter_mut()
+                .find(|page| page.content.full_path == update.path)
+                .ok_or_else(|| format!("Unknown page {}", update.path))?;
+            Self::update_content(&mut page.content, update)?;
+        }
+        site.metadata.extend(self.metadata);
+        for file in self.files {
+            let path = permalink::output_path(&file.path)
+                .map_err(|error| format!("invalid file path \"{}\": {}", file.path, error))?;
+            if site.output().contains_key(&path) {
+                return Err(format!("file {} is already in the output", path));
+            }
+            // A page's index.html takes the path of its directory
+            site.claim_path(path.strip_suffix("index.html").unwrap_or(&path), source)?;
+            site.add_file(path, file.contents);
+        }
+        Ok(())
+    }
+}
+
+impl Plugin for ExternalPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Runs before anything is rendered so that changes to posts and pages show up in the output
+    fn on_content_loaded(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+        let stdout = self.execute(&serde_json::to_string(&SitePayload::new(site))?)?;
+        if stdout.trim().is_empty() {
+            return Ok(());
+        }
+
+        let response: PluginResponse = serde_json::from_str(&stdout)
+            .map_err(|error| format!("{} returned invalid JSON: {}", self.name, error))?;
+        response.apply(site, &self.name).map_err(|error| format!("{}: {}", self.name, error))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Post;
+
+    fn site() -> Site {
+        let markdown = "---\ntitle: Hello\ndate: 2024-01-01\n---\n\nHello";
+        let mut site = Site::new();
+        site.posts.push(Post::for_test(markdown, "posts/hello.md"));
+        site
+    }
+
+    fn plugin(command: &str) -> ExternalPlugin {
+        let mut parts = command.split_whitespace().map(str::to_string);
+        ExternalPlugin {
+            name: command.to_string(),
+            program: parts.next().unwrap(),
+            args: parts.collect(),
+        }
+    }
+
+    #[test]
+    fn test_apply_response() {
+        let mut site = site();
+        let response: PluginResponse = serde_json::from_str(
+            r#"{
+                "posts": [{"path": "posts/hello.md", "html": "<p>Changed</p>", "frontmatter": {"description": "Added"}}],
+                "metadata": {"reading_time": "1 min"},
+                "files": [{"path": "/robots.txt", "contents": "User-agent: *"}]
+            }"#,
+        )
+        .unwrap();
+
+        response.apply(&mut site, "external test").unwrap();
+
+        assert_eq!(site.posts[0].content.html, "<p>Changed</p>");
+        assert_eq!(site.posts[0].content.frontmatter.title, "Hello");
+        assert_eq!(site.posts[0].content.frontmatter.description.as_deref(), Some("Added"));
+        assert_eq!(site.metadata["reading_time"], "1 min");
+        assert_eq!(site.output()["robots.txt"], b"User-agent: *");
+    }
+
+    #[test]
+    fn test_apply_response_rejects_unknown_content() {
+        let mut site = site();
+        let response: PluginResponse = serde_json::from_str(r#"{"pages": [{"path": "pages/missing.md"}]}"#).unwrap();
+
+        assert!(response.apply(&mut site, "external test").is_err());
+    }
+
+    #[test]
+    fn test_apply_response_rejects_fixed_fields() {
+        let mut site = site();
+        let unchanged: PluginResponse =
+            serde_json::from_str(r#"{"posts": [{"path": "posts/hello.md", "frontmatter": {"title": "Hello"}}]}"#).unwrap();
+        assert!(unchanged.apply(&mut site, "external test").is_ok());
+
+        let response: PluginResponse =
+            serde_json::from_str(r#"{"posts": [{"path": "posts/hello.md", "frontmatter": {"permalink": "moved"}}]}"#).unwrap();
+        let error = response.apply(&mut site, "external test").unwrap_err();
+        assert!(error.contains("\"permalink\" can't be changed"), "{}", error);
+        assert_eq!(site.posts[0].content.frontmatter.permalink, None);
+
+        // Fields use the same keys as the frontmatter
+        let response: PluginResponse =
+            serde_json::from_str(r#"{"posts": [{"path": "posts/hello.md", "frontmatter": {"translationKey": "moved"}}]}"#)
+                .unwrap();
+        assert!(response.apply(&mut site, "external test").unwrap_err().contains("\"translationKey\" can't be changed"));
+        let response: PluginResponse =
+            serde_json::from_str(r#"{"posts": [{"path": "posts/hello.md", "frontmatter": {"lightTheme": true}}]}"#).unwrap();
+        response.apply(&mut site, "external test").unwrap();
+        assert!(site.posts[0].content.frontmatter.light_theme);
+    }
+
+    #[test]
+    fn test_apply_response_checks_file_paths() {
+        let files = |path: &str| -> PluginResponse {
+            serde_json::from_value(serde_json::json!({"files": [{"path": path, "contents": "x"}]})).unwrap()
+        };
+        let mut site = site();
+        site.claim_path("hello", "posts/hello.md").unwrap();
+
+        assert!(files("../../etc/x").apply(&mut site, "external test").is_err());
+        assert!(files("hello/index.html").apply(&mut site, "external test").is_err());
+        assert!(files("extra/index.html").apply(&mut site, "external test").is_ok());
+        assert!(files("/extra/index.html").apply(&mut site, "external test").is_err());
+        assert!(!site.output().keys().any(|path| path.contains("..")));
+    }
+
+    #[test]
+    fn test_external_plugin_round_trip() {
+        let mut site = site();
+
+        // Echoing the payload back leaves the site as it was
+        plugin("cat").on_content_loaded(&mut site).unwrap();
+        assert_eq!(site.posts[0].content.html, "<p>Hello</p>");
+
+        assert!(plugin("false").on_content_loaded(&mut site).is_err());
+    }
+}
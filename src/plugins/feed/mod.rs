@@ -1,28 +1,42 @@
-use std::fs::{self, File};
-use std::io::Write;
-use std::env;
+use std::fs;
 use dotenv;
-use crate::parser::Post;
-use crate::plugin::{Plugin, Site};
+use crate::i18n;
+use crate::parser;
+use crate::permalink;
+use crate::plugin::{Plugin, PluginError, Site};
+use crate::render;
 
 // Template filepaths
 const FEED_TEMPLATE_FILE_PATH: &str = "./assets/templates/feed.xml";
+const FEED_ENTRY_PARTIAL_FILE_PATH: &str = "./assets/templates/feed-entry.xml";
 
 // Templates placeholders
 const HOST_PLACEHOLDER: &str = "{host}";
+const HOME_URL_PLACEHOLDER: &str = "{home_url}";
 const WEBSITE_NAME: &str = "{website_name}";
-const WEBSITE_DESCRIPTION: &str = "{website_description}";
-const POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER: &str = "{post_date_timestamp}";
-const POST_ITEM_TITLE_PLACEHOLDER: &str = "{post_title}";
-const POST_ITEM_DESCRIPTION_PLACEHOLDER: &str = "{post_description}";
-const POST_ITEM_URL_PLACEHOLDER: &str = "{post_url}";
+const AUTHOR_NAME: &str = "{author_name}";
+const LAST_UPDATED_PLACEHOLDER: &str = "{last_updated_feed_time}";
+const ENTRIES_PLACEHOLDER: &str = "{entries}";
 
+// Feed entry partial
+const ENTRY_TITLE_PLACEHOLDER: &str = "{title_placeholder}";
+const ENTRY_LINK_PLACEHOLDER: &str = "{link_placeholder}";
+const ENTRY_ID_PLACEHOLDER: &str = "{id_placeholder}";
+const ENTRY_UPDATED_PLACEHOLDER: &str = "{updated_placeholder}";
+const ENTRY_SUMMARY_PLACEHOLDER: &str = "{summary_placeholder}";
+
+/// Generates an Atom feed of listed posts for every language
 pub struct FeedPlugin;
 
 impl FeedPlugin {
     pub fn new() -> Self {
         FeedPlugin
     }
+
+    /// Atom dates are RFC 3339 timestamps, posts only have a day
+    fn format_date(date: &str) -> String {
+        format!("{}T00:00:00Z", parser::day_of(date))
+    }
 }
 
 impl Plugin for FeedPlugin {
@@ -30,51 +44,57 @@ impl Plugin for FeedPlugin {
         "feed"
     }
 
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+    fn requires(&self) -> &[&str] {
+        &["build", "posts"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
         // Get environment variables
         let website_name = dotenv::var("WEBSITE_NAME").expect("WEBSITE_NAME environment variable must be set");
-        let website_description = dotenv::var("WEBSITE_DESCRIPTION").expect("WEBSITE_DESCRIPTION environment variable must be set");
+        let author_name = dotenv::var("AUTHOR_NAME").expect("AUTHOR_NAME environment variable must be set");
         let host = dotenv::var("HOST").expect("HOST environment variable must be set");
 
-        // Read the feed template
+        // Read the feed templates
         let feed_template = fs::read_to_string(FEED_TEMPLATE_FILE_PATH)?;
+        let entry_template = fs::read_to_string(FEED_ENTRY_PARTIAL_FILE_PATH)?;
 
-        // Generate feed items
-        let mut feed_items = String::new();
-        for post in &site.posts {
-            feed_items.push_str(&format!(
-                "    <item>
-        <title>{}</title>
-        <link>{}{}</link>
-        <description>{}</description>
-        <pubDate>{}</pubDate>
-        <guid>{}{}</guid>
-    </item>\n",
-                post.frontmatter.title,
-                host,
-                post.permalink,
-                post.frontmatter.description.as_deref().unwrap_or(""),
-                post.frontmatter.date,
-                host,
-                post.permalink
-            ));
-        }
+        let languages = i18n::languages();
+        for lang in &languages {
+            // Generate feed entries
+            let mut entries = String::new();
+            for post in site.listed_posts_in(lang) {
+                let post_url = permalink::url(&host, &post.permalink);
+                entries.push_str(
+                    &entry_template
+                        .replace(ENTRY_TITLE_PLACEHOLDER, &render::escape(&post.content.frontmatter.title))
+                        .replace(ENTRY_LINK_PLACEHOLDER, &post_url)
+                        .replace(ENTRY_ID_PLACEHOLDER, &post_url)
+                        .replace(ENTRY_UPDATED_PLACEHOLDER, &Self::format_date(&post.content.frontmatter.date))
+                        .replace(ENTRY_SUMMARY_PLACEHOLDER, &render::escape(post.content.summary())),
+                );
+                entries.push('\n');
+            }
+
+            // Posts are sorted newest first, an empty feed was updated today
+            let last_updated = site
+                .listed_posts_in(lang)
+                .next()
+                .map(|post| post.content.frontmatter.date.clone())
+                .unwrap_or_else(parser::today);
 
-        // Replace placeholders in the template
-        let feed_xml = feed_template
-            .replace(HOST_PLACEHOLDER, &host)
-            .replace(WEBSITE_NAME, &website_name)
-            .replace(WEBSITE_DESCRIPTION, &website_description)
-            .replace("{feed_items}", &feed_items);
+            // Replace placeholders in the template
+            let home_url = permalink::url(&host, &i18n::localize(lang, "", &languages[0]));
+            let feed_xml = feed_template
+                .replace(HOME_URL_PLACEHOLDER, &home_url)
+                .replace(HOST_PLACEHOLDER, &host)
+                .replace(WEBSITE_NAME, &render::escape(&website_name))
+                .replace(AUTHOR_NAME, &render::escape(&author_name))
+                .replace(LAST_UPDATED_PLACEHOLDER, &Self::format_date(&last_updated))
+                .replace(ENTRIES_PLACEHOLDER, &entries);
 
-        // Write the feed file
-        let binding = env::current_dir().unwrap().into_os_string();
-        let current_path = binding.to_str().unwrap();
-        let build_path = format!("{}/build/", current_path);
-        let feed_path = format!("{}feed.xml", build_path);
-        let mut file = File::create(feed_path)?;
-        write!(file, "{}", feed_xml)?;
+            site.add_file(i18n::localize(lang, "feed.xml", &languages[0]), feed_xml);
+        }
 
         Ok(())
     }
-} 
\ No newline at end of file
+}
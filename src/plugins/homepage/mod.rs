@@ -1,14 +1,11 @@
 use std::fs;
-use std::fs::File;
-use std::io::Write;
-use std::env;
 use dotenv;
-use crate::parser::Post;
-use crate::plugin::{Plugin, Site};
+use crate::i18n;
+use crate::plugin::{Placeholders, Plugin, PluginError, Site};
+use crate::post_item;
 
 // Template filepaths
 const HOMEPAGE_TEMPLATE_FILE_PATH: &str = "./assets/templates/homepage.html";
-const HOMEPAGE_POST_PARTIAL_FILE_PATH: &str = "./assets/templates/archive-item.html";
 
 // Templates placeholders
 const POST_ITEMS_PLACEHOLDER: &str = "{post_items}";
@@ -18,13 +15,6 @@ const WEBSITE_LOGO_URL: &str = "{website_logo_url}";
 const WEBSITE_DESCRIPTION: &str = "{website_description}";
 const AUTHOR_NAME: &str = "{author_name}";
 
-// Homepage Item partial
-const POST_ITEM_LINK_PLACEHOLDER: &str = "{post_link}";
-const POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER: &str = "{post_date_timestamp}";
-const POST_ITEM_DATE_READABLE_PLACEHOLDER: &str = "{post_date_human_readable}";
-const POST_ITEM_TITLE_PLACEHOLDER: &str = "{post_title}";
-const POST_ITEM_EXCERPT_PLACEHOLDER: &str = "{post_excerpt}";
-
 pub struct HomepagePlugin;
 
 impl HomepagePlugin {
@@ -38,12 +28,11 @@ impl Plugin for HomepagePlugin {
         "homepage"
     }
 
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
-        let binding = env::current_dir().unwrap().into_os_string();
-        let current_path = binding.to_str().unwrap();
-        let build_path = format!("{}/build/", current_path);
-        let file_path = format!("{}index.html", build_path);
+    fn requires(&self) -> &[&str] {
+        &["build", "posts"]
+    }
 
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
         // Get environment variables
         let website_name = dotenv::var("WEBSITE_NAME").expect("WEBSITE_NAME environment variable must be set");
         let author_name = dotenv::var("AUTHOR_NAME").expect("AUTHOR_NAME environment variable must be set");
@@ -51,41 +40,36 @@ impl Plugin for HomepagePlugin {
         let website_logo_url = dotenv::var("WEBSITE_LOGO_URL").expect("WEBSITE_LOGO_URL environment variable must be set");
         let website_description = dotenv::var("WEBSITE_DESCRIPTION").expect("WEBSITE_DESCRIPTION environment variable must be set");
 
-        // Read the post item template
-        let post_item_template = fs::read_to_string(HOMEPAGE_POST_PARTIAL_FILE_PATH)?;
-        let mut post_items = String::new();
+        // Read the templates
+        let post_item_template = fs::read_to_string(post_item::POST_ITEM_PARTIAL_FILE_PATH)?;
+        let homepage_template = fs::read_to_string(HOMEPAGE_TEMPLATE_FILE_PATH)?;
 
-        // Generate post items
-        for post in &site.posts {
-            // Format date for human readable display (YYYY/MM/DD)
-            let date_human_readable = post.frontmatter.date.replace('-', "/");
-            // Build full post URL
-            let post_url = format!("{}/{}/", host, post.permalink);
+        // Every language gets its own homepage, the default one at the root
+        let languages = i18n::languages();
+        let mut rendered_pages = Vec::new();
+        for lang in &languages {
+            // Generate post items
+            let mut post_items = String::new();
+            for post in site.listed_posts_in(lang) {
+                let post_item = post_item::render_post_item(&post_item_template, &host, post);
+                post_items.push_str(&post_item);
+            }
 
-            let post_item = post_item_template
-                .replace(POST_ITEM_LINK_PLACEHOLDER, &post_url)
-                .replace(POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER, &post.frontmatter.date)
-                .replace(POST_ITEM_DATE_READABLE_PLACEHOLDER, &date_human_readable)
-                .replace(POST_ITEM_TITLE_PLACEHOLDER, &post.frontmatter.title)
-                .replace(POST_ITEM_EXCERPT_PLACEHOLDER, &post.frontmatter.description.as_deref().unwrap_or(""));
-            post_items.push_str(&post_item);
+            // Values for the placeholders in the homepage template
+            let placeholders: Placeholders = vec![
+                (POST_ITEMS_PLACEHOLDER, post_items),
+                (HOST_PLACEHOLDER, host.clone()),
+                (WEBSITE_NAME, website_name.clone()),
+                (WEBSITE_LOGO_URL, website_logo_url.clone()),
+                (WEBSITE_DESCRIPTION, website_description.clone()),
+                (AUTHOR_NAME, author_name.clone()),
+            ];
+            rendered_pages.push((i18n::localize(lang, "index.html", &languages[0]), placeholders));
         }
 
-        // Read the homepage template
-        let mut homepage_template = fs::read_to_string(HOMEPAGE_TEMPLATE_FILE_PATH)?;
-
-        // Replace placeholders in the homepage template
-        homepage_template = homepage_template
-            .replace(POST_ITEMS_PLACEHOLDER, &post_items)
-            .replace(HOST_PLACEHOLDER, &host)
-            .replace(WEBSITE_NAME, &website_name)
-            .replace(WEBSITE_LOGO_URL, &website_logo_url)
-            .replace(WEBSITE_DESCRIPTION, &website_description)
-            .replace(AUTHOR_NAME, &author_name);
-
-        // Write the final HTML
-        let mut file = File::create(file_path)?;
-        write!(file, "{}", homepage_template)?;
+        for (path, placeholders) in rendered_pages {
+            site.add_html_page(path, homepage_template.clone(), placeholders);
+        }
         Ok(())
     }
 } 
\ No newline at end of file
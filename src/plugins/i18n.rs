@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::i18n::{self, Strings};
+use crate::plugin::{Plugin, PluginError, Site, RESERVED_PATHS};
+
+// {i18n.nav.about} prints the `nav.about` string of the page's language
+const STRING_START: &str = "{i18n.";
+// Always available: the language code of the page and the path of its homepage
+const LANG_KEY: &str = "lang";
+const HOME_KEY: &str = "home";
+
+/// Loads the UI strings in `i18n/<lang>.yaml` and fills `{i18n.key}`
+/// placeholders in page templates with the strings of each page's language
+pub struct I18nPlugin {
+    languages: Vec<String>,
+    strings: HashMap<String, Strings>,
+}
+
+impl I18nPlugin {
+    pub fn load() -> Result<Self, String> {
+        let languages = i18n::languages();
+        let strings = i18n::load_strings(Path::new(i18n::STRINGS_DIR), &languages)?;
+        Ok(I18nPlugin { languages, strings })
+    }
+
+    /// Looks `key` up in `lang`, then in the default language
+    fn lookup(&self, lang: &str, key: &str) -> Option<String> {
+        match key {
+            LANG_KEY => Some(lang.to_string()),
+            HOME_KEY if lang == self.languages[0] => Some("/".to_string()),
+            HOME_KEY => Some(format!("/{}/", lang)),
+            _ => [lang, self.languages[0].as_str()]
+                .iter()
+                .find_map(|language| self.strings.get(*language)?.get(key))
+                .cloned(),
+        }
+    }
+
+    fn translate(&self, template: &str, lang: &str) -> Result<String, String> {
+        let mut output = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(STRING_START) {
+            let key_start = start + STRING_START.len();
+            let key_length = rest[key_start..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed {} placeholder", STRING_START))?;
+            let key = &rest[key_start..key_start + key_length];
+            let text = self
+                .lookup(lang, key)
+                .ok_or_else(|| format!("Missing UI string \"{}\" for {}, add it to {}/{}.yaml", key, lang, i18n::STRINGS_DIR, lang))?;
+
+            output.push_str(&rest[..start]);
+            output.push_str(&text);
+            rest = &rest[key_start + key_length + 1..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+impl Plugin for I18nPlugin {
+    fn name(&self) -> &str {
+        "i18n"
+    }
+
+    fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Keeps content out of the homepage, search page, feed and sitemap of every language
+    fn on_config(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+        for lang in &self.languages[1..] {
+            for path in RESERVED_PATHS {
+                site.claim_path(&i18n::localize(lang, path, &self.languages[0]), "a reserved path")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_render_template(&self, _site: &Site, path: &str, template: String) -> Result<String, Box<dyn std::error::Error>> {
+        if !template.contains(STRING_START) {
+            return Ok(template);
+        }
+
+        let lang = i18n::language_of_path(path, &self.languages);
+        Ok(self.translate(&template, lang).map_err(|error| format!("{}: {}", path, error))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin() -> I18nPlugin {
+        let strings = HashMap::from([
+            (
+                "en".to_string(),
+                Strings::from([
+                    ("nav.about".to_string(), "About me".to_string()),
+                    ("search".to_string(), "Search".to_string()),
+                ]),
+            ),
+            ("es".to_string(), Strings::from([("nav.about".to_string(), "Sobre mí".to_string())])),
+        ]);
+        I18nPlugin {
+            languages: vec!["en".to_string(), "es".to_string()],
+            strings,
+        }
+    }
+
+    #[test]
+    fn test_translate_by_path() {
+        let plugin = plugin();
+        let site = Site::new();
+        let html = r#"<html lang="{i18n.lang}"><a href="{i18n.home}">{i18n.nav.about}</a> {i18n.search} {post_title}"#
+            .to_string();
+
+        assert_eq!(
+            plugin.on_render_template(&site, "index.html", html.clone()).unwrap(),
+            r#"<html lang="en"><a href="/">About me</a> Search {post_title}"#
+        );
+        // Strings missing in Spanish fall back to English
+        assert_eq!(
+            plugin.on_render_template(&site, "es/hola/index.html", html).unwrap(),
+            r#"<html lang="es"><a href="/es/">Sobre mí</a> Search {post_title}"#
+        );
+
+        let error = plugin.on_render_template(&site, "es/index.html", "{i18n.missing}".to_string()).unwrap_err();
+        assert!(error.to_string().contains("Missing UI string \"missing\" for es"));
+    }
+
+    #[test]
+    fn test_reserves_paths_of_every_language() {
+        let mut site = Site::new();
+
+        plugin().on_config(&mut site).unwrap();
+
+        assert!(site.claim_path("es/search", "pages/es/search.md").is_err());
+        assert!(site.claim_path("es/about", "pages/about.es.md").is_ok());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use dotenv;
+
+use crate::permalink;
+use crate::plugin::{Plugin, PluginError, Site};
+
+// [text](@/posts/other-post.md) links to the post or page written in that file
+const REFERENCE_START: &str = "=\"@/";
+
+/// Replaces `@/posts/file.md` links in posts and pages with the URL of the
+/// post or page built from that file, failing on files that aren't rendered
+pub struct LinksPlugin;
+
+impl LinksPlugin {
+    pub fn new() -> Self {
+        LinksPlugin
+    }
+
+    /// Replaces every `="@/file.md#fragment"` attribute value with the URL in
+    /// `urls`, keyed by file path, keeping the fragment and query
+    fn resolve_references(html: &str, urls: &HashMap<String, String>) -> Result<String, String> {
+        let mut output = String::new();
+        let mut rest = html;
+        while let Some(start) = rest.find(REFERENCE_START) {
+            let reference_start = start + REFERENCE_START.len();
+            let reference_length = rest[reference_start..].find('"').ok_or("Unclosed @/ link")?;
+            let reference = &rest[reference_start..reference_start + reference_length];
+            let (file, suffix) = reference.split_at(reference.find(['#', '?']).unwrap_or(reference.len()));
+            let url = urls
+                .get(file)
+                .ok_or_else(|| format!("Link to @/{}, which is not a rendered post or page", file))?;
+
+            output.push_str(&rest[..start]);
+            output.push_str("=\"");
+            output.push_str(url);
+            output.push_str(suffix);
+            output.push('"');
+            rest = &rest[reference_start + reference_length + 1..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+impl Plugin for LinksPlugin {
+    fn name(&self) -> &str {
+        "links"
+    }
+
+    fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    /// Posts and pages know their permalinks once they are loaded
+    fn on_content_loaded(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+        let host = dotenv::var("HOST").expect("HOST environment variable must be set");
+        let urls: HashMap<String, String> = site
+            .posts
+            .iter()
+            .map(|post| (post.content.full_path.clone(), permalink::url(&host, &post.permalink)))
+            .chain(site.pages.iter().map(|page| (page.content.full_path.clone(), permalink::url(&host, &page.permalink))))
+            .collect();
+
+        let posts = site.posts.iter_mut().map(|post| &mut post.content);
+        let pages = site.pages.iter_mut().map(|page| &mut page.content);
+        for content in posts.chain(pages).filter(|content| content.html.contains(REFERENCE_START)) {
+            content.html = Self::resolve_references(&content.html, &urls)
+                .map_err(|error| format!("{}: {}", content.full_path, error))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_references() {
+        let urls = HashMap::from([
+            ("posts/2024-01-15-hello.md".to_string(), "https://example.com/hello-world/".to_string()),
+            ("pages/docs/setup.md".to_string(), "https://example.com/docs/setup/".to_string()),
+        ]);
+        let html = r#"<a href="@/posts/2024-01-15-hello.md">Hello</a> <a href="@/pages/docs/setup.md#install">Setup</a>"#;
+
+        assert_eq!(
+            LinksPlugin::resolve_references(html, &urls).unwrap(),
+            r#"<a href="https://example.com/hello-world/">Hello</a> <a href="https://example.com/docs/setup/#install">Setup</a>"#
+        );
+
+        let error = LinksPlugin::resolve_references(r#"<a href="@/posts/missing.md">"#, &urls).unwrap_err();
+        assert_eq!(error, "Link to @/posts/missing.md, which is not a rendered post or page");
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use minify_html::Cfg;
+
+use crate::assets;
+use crate::plugin::{Plugin, PluginError, Site};
+
+/// Minifies every HTML page once the other plugins are done with it:
+/// collapses whitespace, strips comments and minifies inline `<style>` and
+/// `<script>`. `<pre>` and `<textarea>` keep their whitespace.
+pub struct MinifyPlugin {
+    config: Cfg,
+    pages: AtomicUsize,
+    original_bytes: AtomicUsize,
+    minified_bytes: AtomicUsize,
+}
+
+impl MinifyPlugin {
+    pub fn new() -> Self {
+        MinifyPlugin {
+            config: Cfg {
+                keep_closing_tags: true,
+                keep_html_and_head_opening_tags: true,
+                minify_css: true,
+                minify_js: true,
+                ..Cfg::spec_compliant()
+            },
+            pages: AtomicUsize::new(0),
+            original_bytes: AtomicUsize::new(0),
+            minified_bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the plugin if `MINIFY_HTML` is turned on
+    pub fn from_config() -> Result<Option<Self>, String> {
+        Ok(assets::flag("MINIFY_HTML")?.then(Self::new))
+    }
+}
+
+impl Plugin for MinifyPlugin {
+    fn name(&self) -> &str {
+        "minify"
+    }
+
+    fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    fn on_render_page(&self, _site: &Site, _path: &str, html: String) -> Result<String, Box<dyn std::error::Error>> {
+        let minified = minify_html::minify(html.as_bytes(), &self.config);
+        self.pages.fetch_add(1, Ordering::Relaxed);
+        self.original_bytes.fetch_add(html.len(), Ordering::Relaxed);
+        self.minified_bytes.fetch_add(minified.len(), Ordering::Relaxed);
+        Ok(String::from_utf8(minified)?)
+    }
+
+    fn on_build_finished(&self, _site: &Site) -> Result<(), Box<dyn std::error::Error>> {
+        let original_bytes = self.original_bytes.load(Ordering::Relaxed);
+        let minified_bytes = self.minified_bytes.load(Ordering::Relaxed);
+        let saved_bytes = original_bytes.saturating_sub(minified_bytes);
+        eprintln!(
+            "Minified {} HTML pages: {} bytes saved ({} to {} bytes, {:.1}% smaller)",
+            self.pages.load(Ordering::Relaxed),
+            saved_bytes,
+            original_bytes,
+            minified_bytes,
+            saved_bytes as f64 * 100.0 / original_bytes.max(1) as f64
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_page() {
+        let plugin = MinifyPlugin::new();
+        let site = Site::new();
+        let html = concat!(
+            "<!doctype html>\n<html lang=\"en\">\n<head>\n    <!-- Styles -->\n",
+            "    <style>\n        body { color: #ff0000; }\n    </style>\n</head>\n<body>\n",
+            "    <p>\n        Some   text\n    </p>\n",
+            "    <pre><code>fn main() {\n    println!(\"hi\");\n}</code></pre>\n",
+            "    <textarea>  keep\n  me </textarea>\n",
+            "    <script>\n        const greeting = \"hi\";\n        console.log(greeting);\n    </script>\n",
+            "</body>\n</html>\n"
+        );
+
+        let minified = plugin.on_render_page(&site, "index.html", html.to_string()).unwrap();
+
+        assert!(!minified.contains("Styles"));
+        assert!(minified.contains("<style>body{color:red}</style>"));
+        assert!(minified.contains("<p>Some text</p>"));
+        assert!(minified.contains("<pre><code>fn main() {\n    println!(\"hi\");\n}</code></pre>"));
+        assert!(minified.contains("<textarea>  keep\n  me </textarea>"));
+        assert!(minified.contains("<script>const greeting=`hi`;console.log(greeting)</script>"));
+        assert_eq!(plugin.pages.load(Ordering::Relaxed), 1);
+        assert_eq!(plugin.original_bytes.load(Ordering::Relaxed), html.len());
+        assert_eq!(plugin.minified_bytes.load(Ordering::Relaxed), minified.len());
+    }
+}
@@ -1,17 +1,41 @@
+pub mod accessibility;
+pub mod aliases;
+pub mod archive;
 pub mod build;
+pub mod data;
+pub mod external;
 pub mod feed;
 pub mod homepage;
+pub mod i18n;
+pub mod links;
+pub mod minify;
+pub mod page;
 pub mod pages;
 pub mod post;
 pub mod posts;
+pub mod related;
+pub mod script;
 pub mod search;
+pub mod series;
 pub mod sitemap;
 
+pub use accessibility::AccessibilityPlugin;
+pub use aliases::AliasesPlugin;
+pub use archive::ArchivePlugin;
 pub use build::BuildPlugin;
+pub use data::DataPlugin;
+pub use external::ExternalPlugin;
 pub use feed::FeedPlugin;
 pub use homepage::HomepagePlugin;
+pub use i18n::I18nPlugin;
+pub use links::LinksPlugin;
+pub use minify::MinifyPlugin;
+pub use page::PagePlugin;
 pub use pages::PagesPlugin;
 pub use post::PostPlugin;
 pub use posts::PostsPlugin;
+pub use related::RelatedPlugin;
+pub use script::ScriptPlugin;
 pub use search::SearchPlugin;
+pub use series::SeriesPlugin;
 pub use sitemap::SitemapPlugin; 
\ No newline at end of file
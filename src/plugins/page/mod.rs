@@ -0,0 +1,157 @@
+use std::fs;
+
+use dotenv;
+
+use crate::i18n;
+use crate::permalink;
+use crate::plugin::{Page, Placeholders, Plugin, PluginError, Site};
+use crate::plugins::pages;
+
+const PAGE_TEMPLATE_FILE_PATH: &str = "./assets/templates/page.html";
+
+// Template placeholders
+const PAGE_TITLE_PLACEHOLDER: &str = "{page_title}";
+const PAGE_DESCRIPTION_PLACEHOLDER: &str = "{page_description}";
+const PAGE_CONTENT_PLACEHOLDER: &str = "{page_content}";
+const PAGE_URL_PLACEHOLDER: &str = "{page_url}";
+const PAGE_SLUG_PLACEHOLDER: &str = "{page_slug}";
+const HOST_PLACEHOLDER: &str = "{host}";
+const WEBSITE_NAME_PLACEHOLDER: &str = "{website_name}";
+const WEBSITE_LOGO_URL_PLACEHOLDER: &str = "{website_logo_url}";
+const AUTHOR_NAME_PLACEHOLDER: &str = "{author_name}";
+const PAGE_BREADCRUMBS_PLACEHOLDER: &str = "{page_breadcrumbs}";
+const PAGE_CHILDREN_PLACEHOLDER: &str = "{page_children}";
+const PAGE_SIBLINGS_PLACEHOLDER: &str = "{page_siblings}";
+const PAGE_KEYWORDS_PLACEHOLDER: &str = "{page_keywords}";
+const PAGE_IMAGE_URL_PLACEHOLDER: &str = "{page_image_url}";
+const THEME_CLASS_PLACEHOLDER: &str = "{theme_class}";
+const ROBOTS_META_PLACEHOLDER: &str = "{robots_meta}";
+const DRAFT_BANNER_PLACEHOLDER: &str = "{draft_banner}";
+const HREFLANG_LINKS_PLACEHOLDER: &str = "{hreflang_links}";
+
+// Only filled in for drafts rendered with --drafts
+const DRAFT_ROBOTS_META: &str = r#"<meta name="robots" content="noindex, nofollow">"#;
+const DRAFT_BANNER: &str = r#"<div class="draft-banner" role="status">Draft preview: this page is not published</div>"#;
+
+/// Renders the pages loaded by `PagesPlugin`
+pub struct PagePlugin;
+
+impl PagePlugin {
+    pub fn new() -> Self {
+        PagePlugin
+    }
+
+    /// Renders "Home / Section / Page" for pages inside a section
+    fn render_breadcrumbs(pages: &[Page], page: &Page, host: &str, website_name: &str, home: &str) -> String {
+        if page.parent.is_none() {
+            return String::new();
+        }
+
+        let mut items = format!(r#"<li><a href="{}">{}</a></li>"#, permalink::url(host, home), website_name);
+        for ancestor in pages::ancestors(pages, page).iter().rev() {
+            items.push_str(&format!(
+                r#"<li><a href="{}">{}</a></li>"#,
+                permalink::url(host, &ancestor.permalink),
+                ancestor.content.frontmatter.title
+            ));
+        }
+        items.push_str(&format!(r#"<li aria-current="page">{}</li>"#, page.content.frontmatter.title));
+
+        format!(r#"<nav class="breadcrumbs" aria-label="Breadcrumb"><ol>{}</ol></nav>"#, items)
+    }
+
+    fn render_page_list(class: &str, pages: &[&Page], host: &str) -> String {
+        if pages.is_empty() {
+            return String::new();
+        }
+
+        let items: String = pages
+            .iter()
+            .map(|page| {
+                format!(
+                    r#"<li><a href="{}">{}</a></li>"#,
+                    permalink::url(host, &page.permalink),
+                    page.content.frontmatter.title
+                )
+            })
+            .collect();
+        format!(r#"<ul class="{}">{}</ul>"#, class, items)
+    }
+}
+
+impl Plugin for PagePlugin {
+    fn name(&self) -> &str {
+        "page"
+    }
+
+    fn requires(&self) -> &[&str] {
+        &["build", "pages"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        // Get environment variables
+        let host = dotenv::var("HOST").expect("HOST environment variable must be set");
+        let website_name =
+            dotenv::var("WEBSITE_NAME").expect("WEBSITE_NAME environment variable must be set");
+        let website_logo_url = dotenv::var("WEBSITE_LOGO_URL")
+            .expect("WEBSITE_LOGO_URL environment variable must be set");
+        let author_name =
+            dotenv::var("AUTHOR_NAME").expect("AUTHOR_NAME environment variable must be set");
+
+        let languages = i18n::languages();
+        let mut rendered_pages = Vec::new();
+        for page in &site.pages {
+            // Pages can pick another template through `layout`
+            let page_template = fs::read_to_string(page.content.frontmatter.template_path(PAGE_TEMPLATE_FILE_PATH))?;
+            let image_url = match &page.content.frontmatter.image {
+                Some(image) => permalink::file_url(&host, image),
+                None => website_logo_url.clone(),
+            };
+            let (robots_meta, draft_banner) = if page.content.frontmatter.is_draft() {
+                (DRAFT_ROBOTS_META, DRAFT_BANNER)
+            } else {
+                ("", "")
+            };
+
+            // Section navigation
+            let home = i18n::localize(&page.content.lang, "", &languages[0]);
+            let breadcrumbs = Self::render_breadcrumbs(&site.pages, page, &host, &website_name, &home);
+            let children = Self::render_page_list("section-children", &pages::children(&site.pages, page), &host);
+            let siblings = Self::render_page_list("section-siblings", &pages::siblings(&site.pages, page), &host);
+
+            // Values for the placeholders in the template
+            let page_url = permalink::url(&host, &page.permalink);
+            let placeholders: Placeholders = vec![
+                (PAGE_TITLE_PLACEHOLDER, page.content.frontmatter.title.clone()),
+                (PAGE_DESCRIPTION_PLACEHOLDER, page.content.frontmatter.description.clone().unwrap_or_default()),
+                (PAGE_KEYWORDS_PLACEHOLDER, page.content.frontmatter.keywords.clone().unwrap_or_default()),
+                (PAGE_IMAGE_URL_PLACEHOLDER, image_url),
+                (THEME_CLASS_PLACEHOLDER, page.content.frontmatter.theme_class().to_string()),
+                (ROBOTS_META_PLACEHOLDER, robots_meta.to_string()),
+                (DRAFT_BANNER_PLACEHOLDER, draft_banner.to_string()),
+                (
+                    HREFLANG_LINKS_PLACEHOLDER,
+                    i18n::render_hreflang_links(&host, &site.page_translations(page, &languages)),
+                ),
+                (PAGE_CONTENT_PLACEHOLDER, page.content.html.clone()),
+                (PAGE_URL_PLACEHOLDER, page_url),
+                (PAGE_SLUG_PLACEHOLDER, page.slug.clone()),
+                (PAGE_BREADCRUMBS_PLACEHOLDER, breadcrumbs),
+                (PAGE_CHILDREN_PLACEHOLDER, children),
+                (PAGE_SIBLINGS_PLACEHOLDER, siblings),
+                (HOST_PLACEHOLDER, host.clone()),
+                (WEBSITE_NAME_PLACEHOLDER, website_name.clone()),
+                (WEBSITE_LOGO_URL_PLACEHOLDER, website_logo_url.clone()),
+                (AUTHOR_NAME_PLACEHOLDER, author_name.clone()),
+            ];
+
+            rendered_pages.push((format!("{}/index.html", page.permalink), page_template, placeholders));
+        }
+
+        for (path, template, placeholders) in rendered_pages {
+            site.add_html_page(path, template, placeholders);
+        }
+
+        Ok(())
+    }
+}
@@ -1,102 +1,158 @@
-use std::env;
-use std::fs::{self, DirEntry, File};
-use std::io::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use dotenv;
-use markdown::{mdast::Node, CompileOptions, Constructs, Options, ParseOptions};
+use rayon::prelude::*;
 
-use crate::plugin::{Page, Plugin, Site};
+use crate::builder::BuildOptions;
+use crate::content::{self, Content};
+use crate::i18n;
+use crate::parser::ContentKind;
+use crate::permalink;
+use crate::plugin::{Page, Plugin, PluginError, Site};
+use crate::slug;
 
 const PAGES_DIR: &str = "pages";
-const PAGE_TEMPLATE_FILE_PATH: &str = "./assets/templates/page.html";
+// A section's landing page, e.g. pages/docs/_index.md -> /docs/
+const SECTION_INDEX_NAME: &str = "_index";
 
-// Template placeholders
-const PAGE_TITLE_PLACEHOLDER: &str = "{page_title}";
-const PAGE_DESCRIPTION_PLACEHOLDER: &str = "{page_description}";
-const PAGE_CONTENT_PLACEHOLDER: &str = "{page_content}";
-const PAGE_URL_PLACEHOLDER: &str = "{page_url}";
-const PAGE_SLUG_PLACEHOLDER: &str = "{page_slug}";
-const HOST_PLACEHOLDER: &str = "{host}";
-const WEBSITE_NAME_PLACEHOLDER: &str = "{website_name}";
-const WEBSITE_LOGO_URL_PLACEHOLDER: &str = "{website_logo_url}";
-const AUTHOR_NAME_PLACEHOLDER: &str = "{author_name}";
-
-pub struct PagesPlugin;
+pub struct PagesPlugin {
+    options: BuildOptions,
+}
 
 impl PagesPlugin {
-    pub fn new() -> Self {
-        PagesPlugin
-    }
-
-    fn parse_html(markdown_content: &str) -> String {
-        let parse_options = Options {
-            compile: CompileOptions {
-                allow_dangerous_html: true,
-                ..CompileOptions::default()
-            },
-            parse: ParseOptions {
-                constructs: Constructs {
-                    frontmatter: true,
-                    ..Constructs::default()
-                },
-                ..ParseOptions::default()
-            },
-        };
-        markdown::to_html_with_options(markdown_content, &parse_options).unwrap_or_default()
+    pub fn new(options: BuildOptions) -> Self {
+        PagesPlugin { options }
     }
 
-    fn parse_frontmatter(markdown_content: &str) -> (String, Option<String>) {
-        let parse_options = ParseOptions {
-            constructs: Constructs {
-                frontmatter: true,
-                ..Constructs::gfm()
-            },
-            ..ParseOptions::default()
-        };
-
-        let ast = markdown::to_mdast(markdown_content, &parse_options).ok();
-
-        if let Some(node) = ast {
-            if let Some(children) = node.children() {
-                if let Some(Node::Yaml(yaml)) = children.first() {
-                    let parsed: std::collections::BTreeMap<String, String> =
-                        serde_yaml::from_str(&yaml.value).unwrap_or_default();
+    /// Builds the slug from the path below `pages/`: every directory becomes
+    /// a URL segment and `_index.md` stands for the directory itself. A language
+    /// suffix (`about.es.md`) is not part of the slug.
+    fn get_slug_from_path(relative_path: &Path, languages: &[String]) -> String {
+        let mut segments: Vec<String> = relative_path
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.iter())
+            .map(|segment| slug::slugify(&segment.to_string_lossy()))
+            .collect();
+
+        let file_stem = relative_path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (name, _) = i18n::split_language_suffix(&file_stem, languages);
+        if name != SECTION_INDEX_NAME {
+            segments.push(slug::slugify(name));
+        }
 
-                    let title = parsed.get("title").cloned().unwrap_or_default();
-                    let description = parsed.get("description").cloned();
+        segments.join("/")
+    }
 
-                    return (title, description);
-                }
+    /// Uses the custom permalink from frontmatter if provided, otherwise the
+    /// path based slug. A custom permalink that would leave `build/` is an error.
+    fn resolve_permalink(content: &Content, slug: &str, default_language: &str) -> Result<String, String> {
+        match &content.frontmatter.permalink {
+            Some(custom) => {
+                let path = permalink::output_path(custom)
+                    .map_err(|error| format!("invalid permalink \"{}\": {}", custom, error))?;
+                Ok(i18n::localize(&content.lang, &path, default_language))
             }
+            None => Ok(slug.to_string()),
+        }
+    }
+
+    fn parse_page(path: &Path, languages: &[String]) -> Result<Page, String> {
+        let mut content = content::load(path, ContentKind::Page, languages)?;
+        let relative_path = path.strip_prefix(PAGES_DIR).map_err(|error| error.to_string())?;
+        let path_slug = Self::get_slug_from_path(relative_path, languages);
+        if content.frontmatter.translation_key.is_none() {
+            content.translation_key = path_slug.clone();
         }
 
-        (String::new(), None)
+        // Translations live below their language prefix, so they get their own sections
+        let slug = i18n::localize(&content.lang, &path_slug, &languages[0]);
+
+        let permalink = Self::resolve_permalink(&content, &slug, &languages[0])?;
+
+        Ok(Page {
+            content,
+            slug,
+            permalink,
+            parent: None,
+        })
     }
 
-    fn get_slug_from_filename(filename: &str) -> String {
-        filename.trim_end_matches(".md").to_string()
+    /// Walks `pages/` recursively and collects every markdown file
+    fn collect_page_paths(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), PluginError> {
+        for entry in fs::read_dir(dir)?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                Self::collect_page_paths(&path, paths)?;
+            } else if path.extension().is_some_and(|extension| extension == "md") {
+                paths.push(path);
+            }
+        }
+        Ok(())
     }
 
-    fn parse_page(entry: DirEntry) -> Option<Page> {
-        let path = entry.path();
-        let filename = entry.file_name().to_str()?.to_string();
+    /// Parses pages on the thread pool. A page that fails to parse, e.g.
+    /// without frontmatter or a title, fails the build.
+    fn parse_pages(paths: &[PathBuf]) -> Result<Vec<Page>, String> {
+        let languages = i18n::languages();
+        let parsed_pages: Vec<Result<Page, String>> =
+            paths.par_iter().map(|path| Self::parse_page(path, &languages)).collect();
+
+        // Report the first failing page in path order
+        parsed_pages
+            .into_iter()
+            .zip(paths)
+            .map(|(parsed_page, path)| {
+                parsed_page.map_err(|error| format!("There was an error with the page {}: {}", path.display(), error))
+            })
+            .collect()
+    }
 
-        if !filename.ends_with(".md") {
-            return None;
+    /// Links every page to its closest ancestor section page, if any
+    fn assign_parents(pages: &mut [Page]) {
+        let slugs: Vec<String> = pages.iter().map(|page| page.slug.clone()).collect();
+        for page in pages.iter_mut() {
+            let mut ancestor = page.slug.as_str();
+            while let Some((parent, _)) = ancestor.rsplit_once('/') {
+                if slugs.iter().any(|slug| slug == parent) {
+                    page.parent = Some(parent.to_string());
+                    break;
+                }
+                ancestor = parent;
+            }
         }
+    }
+}
 
-        let content = fs::read_to_string(&path).ok()?;
-        let (title, description) = Self::parse_frontmatter(&content);
-        let html = Self::parse_html(&content);
-        let slug = Self::get_slug_from_filename(&filename);
+/// Returns the section pages above `page`, closest first
+pub fn ancestors<'a>(pages: &'a [Page], page: &Page) -> Vec<&'a Page> {
+    let mut ancestors = Vec::new();
+    let mut parent = page.parent.as_deref();
+    while let Some(parent_page) = parent.and_then(|slug| pages.iter().find(|page| page.slug == slug)) {
+        ancestors.push(parent_page);
+        parent = parent_page.parent.as_deref();
+    }
+    ancestors
+}
 
-        Some(Page {
-            title,
-            description,
-            slug,
-            html,
-        })
+pub fn children<'a>(pages: &'a [Page], page: &Page) -> Vec<&'a Page> {
+    pages
+        .iter()
+        .filter(|child| child.parent.as_deref() == Some(page.slug.as_str()))
+        .collect()
+}
+
+pub fn siblings<'a>(pages: &'a [Page], page: &Page) -> Vec<&'a Page> {
+    if page.parent.is_none() {
+        return Vec::new();
     }
+    pages
+        .iter()
+        .filter(|sibling| sibling.parent == page.parent && sibling.slug != page.slug)
+        .collect()
 }
 
 impl Plugin for PagesPlugin {
@@ -104,59 +160,95 @@ impl Plugin for PagesPlugin {
         "pages"
     }
 
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
-        // Get environment variables
-        let host = dotenv::var("HOST").expect("HOST environment variable must be set");
-        let website_name =
-            dotenv::var("WEBSITE_NAME").expect("WEBSITE_NAME environment variable must be set");
-        let website_logo_url = dotenv::var("WEBSITE_LOGO_URL")
-            .expect("WEBSITE_LOGO_URL environment variable must be set");
-        let author_name =
-            dotenv::var("AUTHOR_NAME").expect("AUTHOR_NAME environment variable must be set");
-
-        // Read the page template
-        let page_template = fs::read_to_string(PAGE_TEMPLATE_FILE_PATH)?;
-
-        // Get build directory
-        let current_path = env::current_dir()?.into_os_string();
-        let current_path_str = current_path.to_str().ok_or("Invalid path")?;
-        let build_dir = format!("{}/build", current_path_str);
-
-        // Read and process all pages
-        let pages_dir = fs::read_dir(PAGES_DIR)?;
-
-        for entry in pages_dir.flatten() {
-            if let Some(page) = Self::parse_page(entry) {
-                // Create output directory
-                let page_dir = format!("{}/{}", build_dir, page.slug);
-                fs::create_dir_all(&page_dir)?;
-
-                // Apply template
-                let page_url = format!("{}/{}/", host, page.slug);
-                let page_html = page_template
-                    .replace(PAGE_TITLE_PLACEHOLDER, &page.title)
-                    .replace(
-                        PAGE_DESCRIPTION_PLACEHOLDER,
-                        page.description.as_deref().unwrap_or(""),
-                    )
-                    .replace(PAGE_CONTENT_PLACEHOLDER, &page.html)
-                    .replace(PAGE_URL_PLACEHOLDER, &page_url)
-                    .replace(PAGE_SLUG_PLACEHOLDER, &page.slug)
-                    .replace(HOST_PLACEHOLDER, &host)
-                    .replace(WEBSITE_NAME_PLACEHOLDER, &website_name)
-                    .replace(WEBSITE_LOGO_URL_PLACEHOLDER, &website_logo_url)
-                    .replace(AUTHOR_NAME_PLACEHOLDER, &author_name);
-
-                // Write output file
-                let output_path = format!("{}/index.html", page_dir);
-                let mut file = File::create(output_path)?;
-                write!(file, "{}", page_html)?;
-
-                // Store page in site for sitemap
-                site.pages.push(page);
-            }
+    fn is_parallel(&self) -> bool {
+        true
+    }
+
+    fn provides(&self) -> &[&str] {
+        &["pages"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        // Read all pages first so that sections know their children
+        let mut page_paths = Vec::new();
+        Self::collect_page_paths(Path::new(PAGES_DIR), &mut page_paths)?;
+        let mut pages = Self::parse_pages(&page_paths)?;
+        pages.retain(|page| self.options.should_render(page.content.state));
+        pages.sort_by(|a, b| a.slug.cmp(&b.slug));
+        Self::assign_parents(&mut pages);
+
+        for page in &pages {
+            site.claim_path(&page.permalink, &page.content.full_path)?;
         }
 
+        // Store pages in site for rendering and the sitemap
+        site.pages = pages;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(slug: &str) -> Page {
+        let markdown = format!("---\ntitle: {}\n---\n", slug);
+        let full_path = format!("pages/{}.md", slug);
+        Page {
+            content: content::parse(&markdown, &full_path, slug, ContentKind::Page, &["en".to_string()]).unwrap(),
+            slug: slug.to_string(),
+            permalink: slug.to_string(),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_get_slug_from_path() {
+        let languages = vec!["en".to_string(), "es".to_string()];
+        assert_eq!(PagesPlugin::get_slug_from_path(Path::new("about-me.md"), &languages), "about-me");
+        assert_eq!(PagesPlugin::get_slug_from_path(Path::new("docs/_index.md"), &languages), "docs");
+        assert_eq!(PagesPlugin::get_slug_from_path(Path::new("docs/Setup Guide.md"), &languages), "docs/setup-guide");
+        assert_eq!(PagesPlugin::get_slug_from_path(Path::new("Projects/foo/_index.md"), &languages), "projects/foo");
+        assert_eq!(PagesPlugin::get_slug_from_path(Path::new("about-me.es.md"), &languages), "about-me");
+        assert_eq!(PagesPlugin::get_slug_from_path(Path::new("docs/_index.es.md"), &languages), "docs");
+        assert_eq!(PagesPlugin::get_slug_from_path(Path::new("notes.old.md"), &languages), "notes-old");
+    }
+
+    #[test]
+    fn test_assign_parents() {
+        let mut pages = vec![page("about-me"), page("docs"), page("docs/setup"), page("docs/guides/deploy")];
+
+        PagesPlugin::assign_parents(&mut pages);
+
+        assert_eq!(pages[0].parent, None);
+        assert_eq!(pages[1].parent, None);
+        assert_eq!(pages[2].parent.as_deref(), Some("docs"));
+        // Without a docs/guides/_index.md the closest section is docs
+        assert_eq!(pages[3].parent.as_deref(), Some("docs"));
+
+        let child_slugs: Vec<&str> = children(&pages, &pages[1]).iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(child_slugs, vec!["docs/setup", "docs/guides/deploy"]);
+        let sibling_slugs: Vec<&str> = siblings(&pages, &pages[2]).iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(sibling_slugs, vec!["docs/guides/deploy"]);
+        assert!(siblings(&pages, &pages[0]).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_permalink() {
+        let languages = vec!["en".to_string(), "es".to_string()];
+        let content = |frontmatter: &str| {
+            let markdown = format!("---\ntitle: About\n{}---\n", frontmatter);
+            content::parse(&markdown, "pages/about.md", "about.md", ContentKind::Page, &languages).unwrap()
+        };
+
+        assert_eq!(PagesPlugin::resolve_permalink(&content(""), "about", "en").unwrap(), "about");
+        assert_eq!(PagesPlugin::resolve_permalink(&content("permalink: /me/\n"), "about", "en").unwrap(), "me");
+        assert_eq!(
+            PagesPlugin::resolve_permalink(&content("permalink: me\nlang: es\n"), "es/about", "en").unwrap(),
+            "es/me"
+        );
+        let error = PagesPlugin::resolve_permalink(&content("permalink: ../../escaped\n"), "about", "en").unwrap_err();
+        assert!(error.contains("invalid permalink \"../../escaped\""), "{}", error);
+    }
+}
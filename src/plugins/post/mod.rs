@@ -1,9 +1,13 @@
-use std::fs::{self, File};
-use std::io::Write;
-use std::env;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs;
 use dotenv;
+use rayon::prelude::*;
 use crate::parser::Post;
-use crate::plugin::{Plugin, Site};
+use crate::i18n;
+use crate::permalink;
+use crate::plugin::{Placeholders, Plugin, PluginError, Site};
+use crate::post_item;
+use crate::render;
 
 // Template filepaths
 const POST_TEMPLATE_FILE_PATH: &str = "./assets/templates/post.html";
@@ -21,6 +25,19 @@ const POST_ITEM_URL_PLACEHOLDER: &str = "{post_url}";
 const POST_ITEM_KEYWORDS_PLACEHOLDER: &str = "{post_keywords}";
 const POST_ITEM_IMAGE_URL_PLACEHOLDER: &str = "{post_image_url}";
 const THEME_CLASS_PLACEHOLDER: &str = "{theme_class}";
+const ROBOTS_META_PLACEHOLDER: &str = "{robots_meta}";
+const DRAFT_BANNER_PLACEHOLDER: &str = "{draft_banner}";
+const POST_NAVIGATION_PLACEHOLDER: &str = "{post_navigation}";
+const SERIES_NAVIGATION_PLACEHOLDER: &str = "{series_navigation}";
+const RELATED_POSTS_PLACEHOLDER: &str = "{related_posts}";
+const HREFLANG_LINKS_PLACEHOLDER: &str = "{hreflang_links}";
+const POST_ITEM_READING_TIME_PLACEHOLDER: &str = "{post_reading_time}";
+const POST_ITEM_WORD_COUNT_PLACEHOLDER: &str = "{post_word_count}";
+const POST_ITEM_EXCERPT_PLACEHOLDER: &str = "{post_excerpt}";
+
+// Only filled in for drafts rendered with --drafts
+const DRAFT_ROBOTS_META: &str = r#"<meta name="robots" content="noindex, nofollow">"#;
+const DRAFT_BANNER: &str = r#"<div class="draft-banner" role="status">Draft preview: this post is not published</div>"#;
 
 pub struct PostPlugin;
 
@@ -28,6 +45,84 @@ impl PostPlugin {
     pub fn new() -> Self {
         PostPlugin
     }
+
+    /// Renders links to the posts published before and after this one
+    fn render_post_navigation(site: &Site, post: &Post, host: &str) -> String {
+        let link = |index: Option<usize>, class: &str, rel: &str, label: &str| match index {
+            Some(index) => {
+                let linked_post = &site.posts[index];
+                format!(
+                    r#"<a class="{}" href="{}" rel="{}"><span>{}</span> {}</a>"#,
+                    class,
+                    permalink::url(host, &linked_post.permalink),
+                    rel,
+                    label,
+                    linked_post.content.frontmatter.title
+                )
+            }
+            None => String::new(),
+        };
+
+        if post.previous.is_none() && post.next.is_none() {
+            return String::new();
+        }
+        format!(
+            r#"<nav class="post-navigation" aria-label="More posts">{}{}</nav>"#,
+            link(post.previous, "previous-post", "prev", "Previous"),
+            link(post.next, "next-post", "next", "Next")
+        )
+    }
+
+    /// Renders the box listing every part of the series the post belongs to
+    fn render_series_navigation(site: &Site, post_index: usize, host: &str) -> String {
+        let Some(series) = site.series.iter().find(|series| series.posts.contains(&post_index)) else {
+            return String::new();
+        };
+
+        let mut items = String::new();
+        for &index in &series.posts {
+            let part = &site.posts[index];
+            if index == post_index {
+                items.push_str(&format!(r#"<li aria-current="page">{}</li>"#, part.content.frontmatter.title));
+            } else {
+                items.push_str(&format!(
+                    r#"<li><a href="{}">{}</a></li>"#,
+                    permalink::url(host, &part.permalink),
+                    part.content.frontmatter.title
+                ));
+            }
+        }
+
+        let position = series.posts.iter().position(|&index| index == post_index).unwrap_or(0) + 1;
+        format!(
+            r#"<aside class="series-navigation"><p>Part {} of {} in <a href="{}">{}</a></p><ol>{}</ol></aside>"#,
+            position,
+            series.posts.len(),
+            permalink::url(host, &series.permalink),
+            series.name,
+            items
+        )
+    }
+
+    /// Renders the related posts with the archive item partial
+    fn render_related_posts(site: &Site, post: &Post, item_template: &str, host: &str) -> String {
+        // Indexes that no longer point at a post are skipped
+        let related_posts: Vec<&Post> = post.related.iter().filter_map(|&index| site.posts.get(index)).collect();
+        if related_posts.is_empty() {
+            return String::new();
+        }
+
+        let mut post_items = String::new();
+        for related_post in related_posts {
+            post_items.push_str(
+                &post_item::render_post_item(item_template, host, related_post),
+            );
+        }
+        format!(
+            r#"<section class="related-posts"><h2>Related posts</h2><ol class="archive-list">{}</ol></section>"#,
+            post_items
+        )
+    }
 }
 
 impl Plugin for PostPlugin {
@@ -35,46 +130,83 @@ impl Plugin for PostPlugin {
         "post"
     }
 
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+    fn requires(&self) -> &[&str] {
+        &["build", "posts", "related"]
+    }
+
+    fn is_parallel(&self) -> bool {
+        true
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
         // Get environment variables
         let website_name = dotenv::var("WEBSITE_NAME").expect("WEBSITE_NAME environment variable must be set");
         let author_name = dotenv::var("AUTHOR_NAME").expect("AUTHOR_NAME environment variable must be set");
         let host = dotenv::var("HOST").expect("HOST environment variable must be set");
 
-        // Read the post template
-        let post_template = fs::read_to_string(POST_TEMPLATE_FILE_PATH)?;
-
+        // Read every template once, posts can pick another one through `layout`
+        let mut templates = HashMap::new();
         for post in &site.posts {
-            let binding = env::current_dir().unwrap().into_os_string();
-            let current_path = binding.to_str().unwrap();
-            let build_path = format!("{}/build/", current_path);
-            let post_dir_path = format!("{}{}", build_path, post.permalink);
-            let file_path = format!("{}/index.html", post_dir_path);
+            let template_path = post.content.frontmatter.template_path(POST_TEMPLATE_FILE_PATH);
+            if let Entry::Vacant(entry) = templates.entry(template_path) {
+                let template = fs::read_to_string(entry.key())?;
+                entry.insert(template);
+            }
+        }
+
+        let related_item_template = fs::read_to_string(post_item::POST_ITEM_PARTIAL_FILE_PATH)?;
+        let languages = i18n::languages();
 
-            fs::create_dir_all(&post_dir_path)?;
-            let mut file = File::create(file_path)?;
+        let rendered_posts: Vec<(String, String, Placeholders)> = site.posts.par_iter().enumerate().map(|(index, post)| {
+            let post_template = &templates[&post.content.frontmatter.template_path(POST_TEMPLATE_FILE_PATH)];
 
             // Format date for human readable display (YYYY/MM/DD)
-            let date_human_readable = post.frontmatter.date.replace('-', "/");
+            let date_human_readable = post.content.frontmatter.date.replace('-', "/");
             // Build full post URL
-            let post_url = format!("{}/{}/", host, post.permalink);
-
-            // Replace placeholders in the template
-            let post_html = post_template
-                .replace(HOST_PLACEHOLDER, &host)
-                .replace(WEBSITE_NAME, &website_name)
-                .replace(AUTHOR_NAME, &author_name)
-                .replace(POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER, &post.frontmatter.date)
-                .replace(POST_ITEM_DATE_READABLE_PLACEHOLDER, &date_human_readable)
-                .replace(POST_ITEM_TITLE_PLACEHOLDER, &post.frontmatter.title)
-                .replace(POST_ITEM_DESCRIPTION_PLACEHOLDER, &post.frontmatter.description.as_deref().unwrap_or(""))
-                .replace(POST_ITEM_CONTENT_PLACEHOLDER, &post.html)
-                .replace(POST_ITEM_URL_PLACEHOLDER, &post_url)
-                .replace(POST_ITEM_KEYWORDS_PLACEHOLDER, &post.frontmatter.keywords.as_deref().unwrap_or(""))
-                .replace(POST_ITEM_IMAGE_URL_PLACEHOLDER, &format!("{}/img/logo.png", host))
-                .replace(THEME_CLASS_PLACEHOLDER, post.frontmatter.theme_class());
-
-            write!(file, "{}", post_html)?;
+            let post_url = permalink::url(&host, &post.permalink);
+            let image_url = permalink::file_url(&host, post.content.frontmatter.image.as_deref().unwrap_or("img/logo.png"));
+            let (robots_meta, draft_banner) = if post.content.frontmatter.is_draft() {
+                (DRAFT_ROBOTS_META, DRAFT_BANNER)
+            } else {
+                ("", "")
+            };
+
+            // Values for the placeholders in the template
+            let placeholders: Placeholders = vec![
+                (HOST_PLACEHOLDER, host.clone()),
+                (WEBSITE_NAME, website_name.clone()),
+                (AUTHOR_NAME, author_name.clone()),
+                (POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER, post.content.frontmatter.date.clone()),
+                (POST_ITEM_DATE_READABLE_PLACEHOLDER, date_human_readable),
+                (POST_ITEM_TITLE_PLACEHOLDER, post.content.frontmatter.title.clone()),
+                (POST_ITEM_DESCRIPTION_PLACEHOLDER, post.content.frontmatter.description.clone().unwrap_or_default()),
+                (POST_ITEM_EXCERPT_PLACEHOLDER, render::escape(post.content.summary())),
+                (POST_ITEM_READING_TIME_PLACEHOLDER, post.content.reading_time.to_string()),
+                (POST_ITEM_WORD_COUNT_PLACEHOLDER, post.content.word_count.to_string()),
+                (POST_ITEM_CONTENT_PLACEHOLDER, post.content.html.clone()),
+                (POST_ITEM_URL_PLACEHOLDER, post_url),
+                (POST_ITEM_KEYWORDS_PLACEHOLDER, post.content.frontmatter.keywords.clone().unwrap_or_default()),
+                (POST_ITEM_IMAGE_URL_PLACEHOLDER, image_url),
+                (THEME_CLASS_PLACEHOLDER, post.content.frontmatter.theme_class().to_string()),
+                (ROBOTS_META_PLACEHOLDER, robots_meta.to_string()),
+                (DRAFT_BANNER_PLACEHOLDER, draft_banner.to_string()),
+                (POST_NAVIGATION_PLACEHOLDER, Self::render_post_navigation(site, post, &host)),
+                (
+                    HREFLANG_LINKS_PLACEHOLDER,
+                    i18n::render_hreflang_links(&host, &site.post_translations(post, &languages)),
+                ),
+                (SERIES_NAVIGATION_PLACEHOLDER, Self::render_series_navigation(site, index, &host)),
+                (
+                    RELATED_POSTS_PLACEHOLDER,
+                    Self::render_related_posts(site, post, &related_item_template, &host),
+                ),
+            ];
+
+            (format!("{}/index.html", post.permalink), post_template.clone(), placeholders)
+        }).collect();
+
+        for (path, template, placeholders) in rendered_posts {
+            site.add_html_page(path, template, placeholders);
         }
         Ok(())
     }
@@ -1,11 +1,77 @@
-use crate::plugin::{Plugin, Site};
-use crate::parser;
+use crate::builder::BuildOptions;
+use crate::i18n;
+use crate::plugin::{Plugin, PluginError, Series, Site};
+use crate::parser::{self, Post, PublicationState};
+use crate::permalink;
+use crate::slug;
 
-pub struct PostsPlugin;
+pub struct PostsPlugin {
+    options: BuildOptions,
+}
 
 impl PostsPlugin {
-    pub fn new() -> Self {
-        PostsPlugin
+    pub fn new(options: BuildOptions) -> Self {
+        PostsPlugin { options }
+    }
+
+    /// Links every listed post to the listed posts around it in the same
+    /// language. Posts are sorted newest first.
+    fn link_neighbours(posts: &mut [Post]) {
+        let mut languages: Vec<String> = posts.iter().map(|post| post.content.lang.clone()).collect();
+        languages.sort();
+        languages.dedup();
+
+        for lang in languages {
+            let listed: Vec<usize> = (0..posts.len())
+                .filter(|&index| posts[index].content.state.is_listed() && posts[index].content.lang == lang)
+                .collect();
+            for (position, &index) in listed.iter().enumerate() {
+                posts[index].next = position.checked_sub(1).map(|newer| listed[newer]);
+                posts[index].previous = listed.get(position + 1).copied();
+            }
+        }
+    }
+
+    /// Resolves the output path of a post against `pattern`, below the
+    /// language prefix for translations. A path that would leave `build/`,
+    /// e.g. through a `..` in the frontmatter `permalink`, is an error.
+    fn resolve_permalink(post: &Post, pattern: &str, default_language: &str) -> Result<String, String> {
+        let path = permalink::resolve(pattern, &post.content.frontmatter, &post.slug)
+            .and_then(|path| permalink::output_path(&path))
+            .map_err(|error| format!("{}: invalid permalink: {}", post.content.full_path, error))?;
+        Ok(i18n::localize(&post.content.lang, &path, default_language))
+    }
+
+    /// Groups listed posts by language and `series`, ordered by `seriesOrder`
+    /// and then by date. Series are sorted by language, in the order of
+    /// `languages`, and then by name.
+    fn collect_series(posts: &[Post], languages: &[String]) -> Vec<Series> {
+        let mut series: Vec<Series> = Vec::new();
+        // Oldest first, so that posts without a seriesOrder follow by date
+        for (index, post) in posts.iter().enumerate().rev() {
+            let Some(name) = post.content.frontmatter.series.as_ref().filter(|_| post.content.state.is_listed()) else {
+                continue;
+            };
+            let lang = &post.content.lang;
+            match series.iter_mut().find(|series| &series.name == name && &series.lang == lang) {
+                Some(series) => series.posts.push(index),
+                None => series.push(Series {
+                    name: name.clone(),
+                    lang: lang.clone(),
+                    permalink: i18n::localize(lang, &format!("series/{}", slug::slugify(name)), &languages[0]),
+                    posts: vec![index],
+                }),
+            }
+        }
+
+        for series in &mut series {
+            series
+                .posts
+                .sort_by_key(|&index| posts[index].content.frontmatter.series_order.unwrap_or(u32::MAX));
+        }
+        let language_order = |lang: &str| languages.iter().position(|language| language == lang);
+        series.sort_by(|a, b| language_order(&a.lang).cmp(&language_order(&b.lang)).then_with(|| a.name.cmp(&b.name)));
+        series
     }
 }
 
@@ -14,18 +80,120 @@ impl Plugin for PostsPlugin {
         "posts"
     }
 
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+    fn provides(&self) -> &[&str] {
+        &["posts"]
+    }
+
+    fn is_parallel(&self) -> bool {
+        true
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
         // Read posts from the posts directory
-        let mut posts = parser::get_posts();
+        let languages = i18n::languages();
+        let mut posts = parser::get_posts(&languages);
 
-        // Filter out drafts (posts with publish: draft)
-        posts.retain(|post| !post.frontmatter.is_draft());
+        // Filter out drafts and future posts unless the build asked for them
+        posts.retain(|post| self.options.should_render(post.content.state));
 
         // Sort posts by date in reverse chronological order (newest first)
         // Date format is "YYYY-MM-DD" so lexicographic comparison works correctly
-        posts.sort_by(|a, b| b.frontmatter.date.cmp(&a.frontmatter.date));
+        // Posts on the same day keep a stable order by file name
+        posts.sort_by(|a, b| {
+            b.content.frontmatter
+                .date
+                .cmp(&a.content.frontmatter.date)
+                .then_with(|| a.content.file_name.cmp(&b.content.file_name))
+        });
+
+        // Resolve output paths against the site's permalink pattern
+        // and below the language prefix for translations
+        let pattern = permalink::post_pattern();
+        for post in &mut posts {
+            post.permalink = Self::resolve_permalink(post, &pattern, &languages[0])?;
+            site.claim_path(&post.permalink, &post.content.full_path)?;
+
+            if post.content.state == PublicationState::Draft {
+                eprintln!("Rendering draft preview: {} -> /{}/", post.content.file_name, post.permalink);
+            }
+        }
+
+        Self::link_neighbours(&mut posts);
+        let series = Self::collect_series(&posts, &languages);
+        for series in &series {
+            site.claim_path(&series.permalink, &format!("series \"{}\"", series.name))?;
+        }
 
         site.posts = posts;
+        site.series = series;
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn languages() -> Vec<String> {
+        vec!["en".to_string(), "es".to_string()]
+    }
+
+    fn post(title: &str, date: &str, extra: &str) -> Post {
+        let markdown = format!("---\ntitle: {}\ndate: {}\n{}---\n", title, date, extra);
+        Post::for_test(&markdown, &format!("posts/{}.md", slug::slugify(title)))
+    }
+
+    #[test]
+    fn test_link_neighbours_skips_unlisted_posts() {
+        // Newest first, like PostsPlugin sorts them
+        let mut posts = vec![
+            post("Newest", "2024-03-01", ""),
+            post("Draft", "2024-02-01", "publish: draft\n"),
+            post("Middle", "2024-01-01", ""),
+            post("Oldest", "2023-01-01", ""),
+        ];
+
+        PostsPlugin::link_neighbours(&mut posts);
+
+        assert_eq!((posts[0].previous, posts[0].next), (Some(2), None));
+        assert_eq!((posts[1].previous, posts[1].next), (None, None));
+        assert_eq!((posts[2].previous, posts[2].next), (Some(3), Some(0)));
+        assert_eq!((posts[3].previous, posts[3].next), (None, Some(2)));
+    }
+
+    #[test]
+    fn test_collect_series() {
+        let posts = vec![
+            post("Wrap up", "2024-04-01", "series: Rust Basics\n"),
+            post("Other", "2024-03-01", ""),
+            post("Setup", "2024-02-01", "series: Rust Basics\nseriesOrder: 1\n"),
+            post("Ownership", "2024-01-01", "series: Rust Basics\nseriesOrder: 2\n"),
+            post("Later", "2023-06-01", "series: Rust Basics\n"),
+            post("Intro", "2023-01-01", "series: A Series\n"),
+            post("Propiedad", "2022-01-01", "series: Rust Basics\nlang: es\n"),
+        ];
+
+        let series = PostsPlugin::collect_series(&posts, &languages());
+
+        assert_eq!(series.len(), 3);
+        assert_eq!((series[0].name.as_str(), series[0].posts.clone()), ("A Series", vec![5]));
+        assert_eq!(series[1].permalink, "series/rust-basics");
+        // Ordered parts first, then the rest by date
+        assert_eq!(series[1].posts, vec![2, 3, 4, 0]);
+        // Translations form a series of their own
+        assert_eq!((series[2].lang.as_str(), series[2].permalink.as_str()), ("es", "es/series/rust-basics"));
+        assert_eq!(series[2].posts, vec![6]);
+    }
+
+    #[test]
+    fn test_resolve_permalink() {
+        let hello = |extra: &str| post("Hello", "2024-03-01", extra);
+
+        assert_eq!(PostsPlugin::resolve_permalink(&hello(""), "/:year/:slug/", "en").unwrap(), "2024/hello");
+        assert_eq!(PostsPlugin::resolve_permalink(&hello("lang: es\n"), "/:slug/", "en").unwrap(), "es/hello");
+
+        let error = PostsPlugin::resolve_permalink(&hello("permalink: ../../escaped\n"), "/:slug/", "en").unwrap_err();
+        assert!(error.starts_with("posts/hello.md: invalid permalink:"), "{}", error);
+        assert!(PostsPlugin::resolve_permalink(&hello(""), "/../:slug/", "en").is_err());
+    }
+}
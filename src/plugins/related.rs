@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+
+use dotenv;
+use rayon::prelude::*;
+
+use crate::parser::Post;
+use crate::plugin::{Plugin, PluginError, Site};
+
+// How many related posts each post gets unless RELATED_POSTS says otherwise
+const DEFAULT_RELATED_POSTS: usize = 3;
+// A shared tag counts more than a shared keyword, which counts more than similar text
+const TAG_WEIGHT: f64 = 3.0;
+const KEYWORD_WEIGHT: f64 = 2.0;
+const TEXT_WEIGHT: f64 = 1.0;
+// Common English words that say nothing about what a post is about
+const STOP_WORDS: &[&str] = &[
+    "about", "also", "and", "any", "are", "but", "can", "for", "from", "has", "have", "how", "into", "its", "just",
+    "not", "our", "out", "than", "that", "the", "their", "them", "then", "there", "they", "this", "was", "what",
+    "when", "which", "who", "will", "with", "you", "your",
+];
+
+/// Term weights of one post's text
+type TermVector = HashMap<String, f64>;
+
+/// Picks the most related listed posts in the same language for every post,
+/// scored by shared `tags`, shared `keywords` and a TF-IDF similarity of their text
+pub struct RelatedPlugin;
+
+impl RelatedPlugin {
+    pub fn new() -> Self {
+        RelatedPlugin
+    }
+
+    /// Reads `RELATED_POSTS`, the number of related posts to show (0 turns them off)
+    fn count() -> Result<usize, String> {
+        match dotenv::var("RELATED_POSTS") {
+            Ok(count) => count
+                .trim()
+                .parse()
+                .map_err(|_| format!("RELATED_POSTS must be a number, got \"{}\"", count)),
+            Err(_) => Ok(DEFAULT_RELATED_POSTS),
+        }
+    }
+
+    /// Lowercase words of the text, with HTML tags and stop words left out
+    fn words(html: &str) -> Vec<String> {
+        let mut text = String::new();
+        let mut in_tag = false;
+        for character in html.chars() {
+            match character {
+                '<' => in_tag = true,
+                '>' => {
+                    in_tag = false;
+                    text.push(' ');
+                }
+                character if !in_tag => text.push(character),
+                _ => {}
+            }
+        }
+
+        text.split(|character: char| !character.is_alphanumeric())
+            .filter(|word| word.chars().count() > 2)
+            .map(str::to_lowercase)
+            .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+            .collect()
+    }
+
+    /// Lowercase `tags` and comma separated `keywords` of a post
+    fn topics(post: &Post) -> (HashSet<String>, HashSet<String>) {
+        let tags = post.content.frontmatter.tags.iter().map(|tag| tag.trim().to_lowercase()).collect();
+        let keywords = post
+            .content.frontmatter
+            .keywords
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(|keyword| keyword.trim().to_lowercase())
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+        (tags, keywords)
+    }
+
+    /// Builds a normalized TF-IDF vector for every document. Words found in
+    /// every document weigh nothing.
+    fn term_vectors(documents: &[Vec<String>]) -> Vec<TermVector> {
+        let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+        for words in documents {
+            let unique: HashSet<&str> = words.iter().map(String::as_str).collect();
+            for word in unique {
+                *document_frequency.entry(word).or_default() += 1;
+            }
+        }
+
+        let total = documents.len() as f64;
+        documents
+            .iter()
+            .map(|words| {
+                let mut vector = TermVector::new();
+                for word in words {
+                    *vector.entry(word.clone()).or_default() += 1.0;
+                }
+                for (word, weight) in vector.iter_mut() {
+                    let idf = (total / document_frequency[word.as_str()] as f64).ln();
+                    *weight = *weight / words.len() as f64 * idf;
+                }
+
+                let norm = vector.values().map(|weight| weight * weight).sum::<f64>().sqrt();
+                if norm > 0.0 {
+                    vector.values_mut().for_each(|weight| *weight /= norm);
+                }
+                vector
+            })
+            .collect()
+    }
+
+    fn cosine_similarity(a: &TermVector, b: &TermVector) -> f64 {
+        let (shorter, longer) = if a.len() < b.len() { (a, b) } else { (b, a) };
+        shorter
+            .iter()
+            .filter_map(|(word, weight)| longer.get(word).map(|other| weight * other))
+            .sum()
+    }
+
+    /// Returns, for every post, the indexes of up to `count` listed posts with a
+    /// positive score, best first. Ties keep the newest post first.
+    fn related_posts(posts: &[Post], count: usize) -> Vec<Vec<usize>> {
+        let topics: Vec<_> = posts.iter().map(Self::topics).collect();
+        let documents: Vec<Vec<String>> = posts
+            .iter()
+            .map(|post| {
+                let mut words = Self::words(&post.content.frontmatter.title);
+                words.extend(Self::words(&post.content.html));
+                words
+            })
+            .collect();
+        let vectors = Self::term_vectors(&documents);
+
+        (0..posts.len())
+            .into_par_iter()
+            .map(|index| {
+                let (tags, keywords) = &topics[index];
+                let mut scores: Vec<(usize, f64)> = (0..posts.len())
+                    .filter(|&other| {
+                        other != index && posts[other].content.state.is_listed() && posts[other].content.lang == posts[index].content.lang
+                    })
+                    .map(|other| {
+                        let (other_tags, other_keywords) = &topics[other];
+                        let score = TAG_WEIGHT * tags.intersection(other_tags).count() as f64
+                            + KEYWORD_WEIGHT * keywords.intersection(other_keywords).count() as f64
+                            + TEXT_WEIGHT * Self::cosine_similarity(&vectors[index], &vectors[other]);
+                        (other, score)
+                    })
+                    .filter(|(_, score)| *score > 0.0)
+                    .collect();
+
+                // Stable, so equal scores stay newest first
+                scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+                scores.into_iter().take(count).map(|(other, _)| other).collect()
+            })
+            .collect()
+    }
+}
+
+impl Plugin for RelatedPlugin {
+    fn name(&self) -> &str {
+        "related"
+    }
+
+    fn requires(&self) -> &[&str] {
+        &["posts"]
+    }
+
+    fn provides(&self) -> &[&str] {
+        &["related"]
+    }
+
+    fn is_parallel(&self) -> bool {
+        true
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        // With RELATED_POSTS=0 every post still gets an empty list, so the
+        // plugins requiring "related" never see stale indexes
+        let count = Self::count()?;
+        let related = if count == 0 {
+            vec![Vec::new(); site.posts.len()]
+        } else {
+            Self::related_posts(&site.posts, count)
+        };
+        for (post, related) in site.posts.iter_mut().zip(related) {
+            post.related = related;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(title: &str, extra: &str, body: &str) -> Post {
+        let markdown = format!("---\ntitle: {}\ndate: 2024-01-01\n{}---\n\n{}", title, extra, body);
+        Post::for_test(&markdown, "posts/test.md")
+    }
+
+    #[test]
+    fn test_words_skip_html() {
+        assert_eq!(
+            RelatedPlugin::words(r#"<p>The borrow <a href="/rust">checker</a> in Rust!</p>"#),
+            vec!["borrow", "checker", "rust"]
+        );
+    }
+
+    #[test]
+    fn test_related_posts() {
+        let posts = vec![
+            post("Ownership", "tags: [rust]\n", "Moving values and the borrow checker."),
+            post("Lifetimes", "tags: rust\nkeywords: borrow checker\n", "Lifetimes keep references valid."),
+            post("Borrowing", "keywords: Borrow Checker, references\n", "The borrow checker tracks references."),
+            post("Gardening", "", "Tomatoes need sun and water."),
+            post("Draft", "tags: rust\npublish: draft\n", "The borrow checker again."),
+        ];
+
+        let related = RelatedPlugin::related_posts(&posts, 2);
+
+        // A shared tag beats shared keywords, and matching text alone is enough
+        assert_eq!(related[0], vec![1, 2]);
+        assert_eq!(related[1], vec![0, 2]);
+        assert_eq!(related[2], vec![1, 0]);
+        // Nothing in common, and drafts are never suggested
+        assert_eq!(related[3], Vec::<usize>::new());
+        assert_eq!(related[4], vec![0, 1]);
+    }
+}
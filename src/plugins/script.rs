@@ -0,0 +1,358 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Scope, AST};
+
+use crate::html;
+use crate::plugin::{Plugin, PluginError, Site};
+use crate::plugins::external::{PluginResponse, SitePayload};
+
+const SCRIPTS_DIR: &str = "scripts";
+// Script functions named like this become template filters and shortcodes
+const FILTER_PREFIX: &str = "filter_";
+const SHORTCODE_PREFIX: &str = "shortcode_";
+// Tags: {% name "arg" %} and {% filter name %}...{% endfilter %}
+const TAG_START: &str = "{%";
+const TAG_END: &str = "%}";
+const FILTER_START: &str = "{% filter ";
+const FILTER_END: &str = "{% endfilter %}";
+// Limits that stop a runaway script instead of hanging the build
+const MAX_OPERATIONS: u64 = 50_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+
+struct Script {
+    path: String,
+    ast: AST,
+}
+
+/// Runs the Rhai scripts in `scripts/` once content is loaded. Scripts see
+/// `posts`, `pages` and `metadata` as variables and can change them, push
+/// `#{path, contents}` maps to `files`, and define `filter_<name>(text)` and
+/// `shortcode_<name>(args)` functions.
+pub struct ScriptPlugin {
+    engine: Engine,
+    scripts: Vec<Script>,
+}
+
+impl ScriptPlugin {
+    /// Compiles every `.rhai` file in `scripts/` in file name order, or returns
+    /// `None` if there are none
+    pub fn load() -> Result<Option<Self>, String> {
+        let scripts_dir = Path::new(SCRIPTS_DIR);
+        if !scripts_dir.is_dir() {
+            return Ok(None);
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(scripts_dir)
+            .map_err(|error| format!("Failed to read {}: {}", SCRIPTS_DIR, error))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "rhai"))
+            .collect();
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        paths.sort();
+
+        let engine = Self::engine();
+        let mut scripts = Vec::new();
+        for path in paths {
+            let ast = engine
+                .compile_file(path.clone())
+                .map_err(|error| format!("Failed to compile {}: {}", path.display(), error))?;
+            scripts.push(Script {
+                path: path.display().to_string(),
+                ast,
+            });
+        }
+
+        Ok(Some(ScriptPlugin { engine, scripts }))
+    }
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        engine
+    }
+
+    /// Calls `function` with one argument, later scripts win over earlier ones.
+    /// Returns `None` if no script defines it.
+    fn call(&self, function: &str, argument: Dynamic) -> Option<Result<String, String>> {
+        let script = self.scripts.iter().rev().find(|script| {
+            script
+                .ast
+                .iter_functions()
+                .any(|definition| definition.name == function && definition.params.len() == 1)
+        })?;
+
+        Some(
+            self.engine
+                .call_fn_with_options::<Dynamic>(
+                    // Only the function runs, not the script's top-level statements
+                    CallFnOptions::new().eval_ast(false),
+                    &mut Scope::new(),
+                    &script.ast,
+                    function,
+                    (argument,),
+                )
+                .map(|result| result.to_string())
+                .map_err(|error| format!("{} in {}: {}", function, script.path, error)),
+        )
+    }
+
+    /// Splits the inside of a tag into its name and arguments. Arguments are
+    /// separated by spaces unless quoted.
+    fn parse_tag(tag: &str) -> (String, Vec<String>) {
+        // Tags in content have been through the HTML renderer
+        let tag = tag
+            .replace("&quot;", "\"")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&");
+
+        let mut words = Vec::new();
+        let mut word = String::new();
+        let mut quoted = false;
+        for character in tag.chars() {
+            match character {
+                '"' => quoted = !quoted,
+                character if character.is_whitespace() && !quoted => {
+                    if !word.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                    }
+                }
+                character => word.push(character),
+            }
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+
+        let name = if words.is_empty() { String::new() } else { words.remove(0) };
+        (name, words)
+    }
+
+    /// Replaces `{% name args %}` tags with the output of `shortcode_<name>`.
+    /// A shortcode alone in a paragraph replaces the paragraph, tags without a
+    /// matching function are left as they are.
+    fn expand_shortcodes(&self, html: &str) -> Result<String, String> {
+        let mut output = String::new();
+        let mut rest = html;
+        while let Some(start) = rest.find(TAG_START) {
+            let Some(length) = rest[start..].find(TAG_END) else {
+                break;
+            };
+            let end = start + length + TAG_END.len();
+            let (name, args) = Self::parse_tag(&rest[start + TAG_START.len()..start + length]);
+
+            let args: Array = args.into_iter().map(Dynamic::from).collect();
+            match self.call(&format!("{}{}", SHORTCODE_PREFIX, name), args.into()) {
+                Some(expanded) => {
+                    let (before, after) = (&rest[..start], &rest[end..]);
+                    if before.ends_with("<p>") && after.starts_with("</p>") {
+                        output.push_str(&before[..before.len() - "<p>".len()]);
+                        output.push_str(&expanded?);
+                        rest = &after["</p>".len()..];
+                    } else {
+                        output.push_str(before);
+                        output.push_str(&expanded?);
+                        rest = after;
+                    }
+                }
+                None => {
+                    output.push_str(&rest[..end]);
+                    rest = &rest[end..];
+                }
+            }
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Replaces `{% filter name %}text{% endfilter %}` with `filter_<name>(text)`
+    fn apply_filters(&self, html: &str) -> Result<String, String> {
+        let mut output = String::new();
+        let mut rest = html;
+        while let Some(start) = rest.find(FILTER_START) {
+            let name_start = start + FILTER_START.len();
+            let name_length = rest[name_start..].find(TAG_END).ok_or("Unclosed filter tag")?;
+            let name = rest[name_start..name_start + name_length].trim();
+            let body_start = name_start + name_length + TAG_END.len();
+            let body_length = rest[body_start..]
+                .find(FILTER_END)
+                .ok_or_else(|| format!("Filter {} has no {}", name, FILTER_END))?;
+
+            let body = &rest[body_start..body_start + body_length];
+            let filtered = self
+                .call(&format!("{}{}", FILTER_PREFIX, name), body.into())
+                .ok_or_else(|| format!("Unknown filter: {}", name))??;
+
+            output.push_str(&rest[..start]);
+            output.push_str(&filtered);
+            rest = &rest[body_start + body_length + FILTER_END.len()..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    fn run_script(&self, script: &Script, site: &mut Site) -> Result<(), String> {
+        let error = |error: Box<rhai::EvalAltResult>| format!("{}: {}", script.path, error);
+
+        let mut scope = Scope::new();
+        let payload = rhai::serde::to_dynamic(SitePayload::new(site)).map_err(error)?;
+        for (name, value) in payload.cast::<rhai::Map>() {
+            scope.push(name, value);
+        }
+        scope.push("files", Array::new());
+
+        self.engine.run_ast_with_scope(&mut scope, &script.ast).map_err(error)?;
+
+        let mut response = rhai::Map::new();
+        for name in ["posts", "pages", "metadata", "files"] {
+            if let Some(value) = scope.get_value::<Dynamic>(name) {
+                response.insert(name.into(), value);
+            }
+        }
+        let response: PluginResponse = rhai::serde::from_dynamic(&response.into()).map_err(error)?;
+        response.apply(site, &script.path).map_err(|message| format!("{}: {}", script.path, message))
+    }
+}
+
+impl Plugin for ScriptPlugin {
+    fn name(&self) -> &str {
+        "scripts"
+    }
+
+    fn run(&self, _site: &mut Site) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    fn on_content_loaded(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+        for script in &self.scripts {
+            self.run_script(script, site)?;
+        }
+
+        // Code samples may show shortcode syntax, only expand it outside of them
+        for post in site.posts.iter_mut() {
+            post.content.html = html::replace_outside_code(&post.content.html, |part| self.expand_shortcodes(part))?;
+        }
+        for page in site.pages.iter_mut() {
+            page.content.html = html::replace_outside_code(&page.content.html, |part| self.expand_shortcodes(part))?;
+        }
+        Ok(())
+    }
+
+    fn on_render_page(&self, _site: &Site, path: &str, html: String) -> Result<String, Box<dyn std::error::Error>> {
+        if !html.contains(FILTER_START) {
+            return Ok(html);
+        }
+        let filtered = html::replace_outside_code(&html, |part| self.apply_filters(part));
+        Ok(filtered.map_err(|error| format!("{}: {}", path, error))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Post;
+
+    fn plugin(source: &str) -> ScriptPlugin {
+        let engine = ScriptPlugin::engine();
+        let ast = engine.compile(source).unwrap();
+        ScriptPlugin {
+            engine,
+            scripts: vec![Script {
+                path: "scripts/test.rhai".to_string(),
+                ast,
+            }],
+        }
+    }
+
+    fn site(body: &str) -> Site {
+        let markdown = format!("---\ntitle: Hello\ndate: 2024-01-01\n---\n\n{}", body);
+        let mut site = Site::new();
+        site.posts.push(Post::for_test(&markdown, "posts/hello.md"));
+        site
+    }
+
+    #[test]
+    fn test_script_changes_site() {
+        let plugin = plugin(
+            r#"
+            // Loop variables are copies, change posts through their index
+            for i in 0..posts.len() {
+                posts[i].html.replace("src=\"/img/", "src=\"https://cdn.example.com/img/");
+                posts[i].frontmatter.description = "Words: " + posts[i].html.split(" ").len();
+            }
+            metadata.generator = "rhai";
+            files.push(#{ path: "robots.txt", contents: "User-agent: *" });
+            "#,
+        );
+        let mut site = site("![logo](/img/logo.png)");
+
+        plugin.on_content_loaded(&mut site).unwrap();
+
+        assert_eq!(site.posts[0].content.html, r#"<p><img src="https://cdn.example.com/img/logo.png" alt="logo" /></p>"#);
+        assert_eq!(site.posts[0].content.frontmatter.description.as_deref(), Some("Words: 4"));
+        assert_eq!(site.metadata["generator"], "rhai");
+        assert_eq!(site.output()["robots.txt"], b"User-agent: *");
+    }
+
+    #[test]
+    fn test_shortcodes() {
+        let plugin = plugin(
+            r#"
+            fn shortcode_youtube(args) {
+                `<iframe src="https://www.youtube.com/embed/${args[0]}" title="${args[1]}"></iframe>`
+            }
+            "#,
+        );
+        let mut site = site("{% youtube abc123 \"A talk\" %}\n\nInline {% youtube x y %} and {% unknown %}\n\n`{% youtube z %}`");
+
+        plugin.on_content_loaded(&mut site).unwrap();
+
+        assert_eq!(
+            site.posts[0].content.html,
+            concat!(
+                r#"<iframe src="https://www.youtube.com/embed/abc123" title="A talk"></iframe>"#,
+                "\n",
+                r#"<p>Inline <iframe src="https://www.youtube.com/embed/x" title="y"></iframe> and {% unknown %}</p>"#,
+                "\n",
+                "<p><code>{% youtube z %}</code></p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        // Top-level statements only run in on_content_loaded, where `posts` exists
+        let plugin = plugin("let count = posts.len();\nfn filter_upper(text) { text.to_upper() }");
+        let site = Site::new();
+
+        let html = plugin
+            .on_render_page(&site, "index.html", "<h1>{% filter upper %}Hello{% endfilter %}</h1>".to_string())
+            .unwrap();
+        assert_eq!(html, "<h1>HELLO</h1>");
+
+        let error = plugin
+            .on_render_page(&site, "index.html", "{% filter lower %}Hello{% endfilter %}".to_string())
+            .unwrap_err();
+        assert!(error.to_string().contains("Unknown filter: lower"));
+
+        // Code samples documenting the syntax are left alone
+        let sample = "<p><code>{% filter escape %}x{% endfilter %}</code></p>".to_string();
+        assert_eq!(plugin.on_render_page(&site, "index.html", sample.clone()).unwrap(), sample);
+    }
+
+    #[test]
+    fn test_runaway_script_stops() {
+        let mut plugin = plugin("let i = 0; loop { i += 1; }");
+        // A lower limit than MAX_OPERATIONS keeps the test fast
+        plugin.engine.set_max_operations(10_000);
+        let mut site = site("Hello");
+
+        let error = plugin.on_content_loaded(&mut site).unwrap_err();
+        assert!(error.to_string().contains("Too many operations"), "{}", error);
+    }
+}
@@ -1,10 +1,12 @@
-use std::env;
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 
 use dotenv;
+use serde::Serialize;
 
-use crate::plugin::{Plugin, Site};
+use crate::i18n;
+use crate::permalink;
+use crate::plugin::{Placeholders, Plugin, PluginError, Site};
+use crate::render;
 
 const SEARCH_TEMPLATE_FILE_PATH: &str = "./assets/templates/search.html";
 
@@ -17,6 +19,20 @@ const AUTHOR_NAME_PLACEHOLDER: &str = "{author_name}";
 const TWITTER_HANDLE_PLACEHOLDER: &str = "{twitter_handle}";
 const RESOURCES_PLACEHOLDER: &str = "{resources}";
 
+/// One post in the search index
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResource<'a> {
+    title: &'a str,
+    url: String,
+    date_timestamp: &'a str,
+    date_human_readable: String,
+    /// HTML-escaped, the search script inserts it as markup
+    excerpt: String,
+    reading_time: usize,
+    word_count: usize,
+}
+
 pub struct SearchPlugin;
 
 impl SearchPlugin {
@@ -29,30 +45,22 @@ impl SearchPlugin {
         date.replace("-", "/")
     }
 
-    fn generate_search_json(site: &Site, host: &str) -> String {
-        let items: Vec<String> = site
-            .posts
-            .iter()
-            .map(|post| {
-                let url = format!("{}/{}/", host, post.permalink);
-                let date_timestamp = &post.frontmatter.date;
-                let date_human_readable = Self::format_date(date_timestamp);
-                let excerpt = post
-                    .frontmatter
-                    .description
-                    .as_deref()
-                    .unwrap_or("")
-                    .replace("\"", "\\\"");
-                let title = post.frontmatter.title.replace("\"", "\\\"");
-
-                format!(
-                    r#"{{"title":"{}","url":"{}","dateTimestamp":"{}","dateHumanReadable":"{}","excerpt":"{}"}}"#,
-                    title, url, date_timestamp, date_human_readable, excerpt
-                )
+    fn generate_search_json(site: &Site, host: &str, lang: &str) -> Result<String, serde_json::Error> {
+        let items: Vec<SearchResource> = site
+            .listed_posts_in(lang)
+            .map(|post| SearchResource {
+                title: &post.content.frontmatter.title,
+                url: permalink::url(host, &post.permalink),
+                date_timestamp: &post.content.frontmatter.date,
+                date_human_readable: Self::format_date(&post.content.frontmatter.date),
+                excerpt: render::escape(post.content.summary()),
+                reading_time: post.content.reading_time,
+                word_count: post.content.word_count,
             })
             .collect();
 
-        format!("[{}]", items.join(","))
+        // The index is embedded in a <script> tag, which a "</" would close
+        Ok(serde_json::to_string(&items)?.replace("</", "<\\/"))
     }
 }
 
@@ -61,7 +69,11 @@ impl Plugin for SearchPlugin {
         "search"
     }
 
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+    fn requires(&self) -> &[&str] {
+        &["build", "posts"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
         // Get environment variables
         let host = dotenv::var("HOST").expect("HOST environment variable must be set");
         let website_name =
@@ -78,28 +90,28 @@ impl Plugin for SearchPlugin {
         // Read the search template
         let search_template = fs::read_to_string(SEARCH_TEMPLATE_FILE_PATH)?;
 
-        // Generate search JSON from posts
-        let resources_json = Self::generate_search_json(site, &host);
-
-        // Replace placeholders
-        let search_html = search_template
-            .replace(HOST_PLACEHOLDER, &host)
-            .replace(WEBSITE_NAME_PLACEHOLDER, &website_name)
-            .replace(WEBSITE_DESCRIPTION_PLACEHOLDER, &website_description)
-            .replace(WEBSITE_LOGO_URL_PLACEHOLDER, &website_logo_url)
-            .replace(AUTHOR_NAME_PLACEHOLDER, &author_name)
-            .replace(TWITTER_HANDLE_PLACEHOLDER, &twitter_handle)
-            .replace(RESOURCES_PLACEHOLDER, &resources_json);
-
-        // Create the search directory and write the file
-        let current_path = env::current_dir()?.into_os_string();
-        let current_path_str = current_path.to_str().ok_or("Invalid path")?;
-        let search_dir = format!("{}/build/search", current_path_str);
-        fs::create_dir_all(&search_dir)?;
-
-        let search_path = format!("{}/index.html", search_dir);
-        let mut file = File::create(search_path)?;
-        write!(file, "{}", search_html)?;
+        // Every language gets a search page over its own posts
+        let languages = i18n::languages();
+        for lang in &languages {
+            let resources_json = Self::generate_search_json(site, &host, lang)?;
+
+            // Values for the placeholders
+            let placeholders: Placeholders = vec![
+                (HOST_PLACEHOLDER, host.clone()),
+                (WEBSITE_NAME_PLACEHOLDER, website_name.clone()),
+                (WEBSITE_DESCRIPTION_PLACEHOLDER, website_description.clone()),
+                (WEBSITE_LOGO_URL_PLACEHOLDER, website_logo_url.clone()),
+                (AUTHOR_NAME_PLACEHOLDER, author_name.clone()),
+                (TWITTER_HANDLE_PLACEHOLDER, twitter_handle.clone()),
+                (RESOURCES_PLACEHOLDER, resources_json),
+            ];
+
+            site.add_html_page(
+                i18n::localize(lang, "search/index.html", &languages[0]),
+                search_template.clone(),
+                placeholders,
+            );
+        }
 
         Ok(())
     }
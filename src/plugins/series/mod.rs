@@ -0,0 +1,81 @@
+use std::fs;
+
+use dotenv;
+
+use crate::permalink;
+use crate::plugin::{Placeholders, Plugin, PluginError, Site};
+use crate::post_item;
+
+// Template filepaths
+const SERIES_TEMPLATE_FILE_PATH: &str = "./assets/templates/series.html";
+
+// Templates placeholders
+const SERIES_TITLE_PLACEHOLDER: &str = "{series_title}";
+const SERIES_URL_PLACEHOLDER: &str = "{series_url}";
+const SERIES_POST_COUNT_PLACEHOLDER: &str = "{series_post_count}";
+const POST_ITEMS_PLACEHOLDER: &str = "{post_items}";
+const HOST_PLACEHOLDER: &str = "{host}";
+const WEBSITE_NAME: &str = "{website_name}";
+const WEBSITE_LOGO_URL: &str = "{website_logo_url}";
+const AUTHOR_NAME: &str = "{author_name}";
+
+/// Generates an index page for every series, listing its posts in reading order
+pub struct SeriesPlugin;
+
+impl SeriesPlugin {
+    pub fn new() -> Self {
+        SeriesPlugin
+    }
+}
+
+impl Plugin for SeriesPlugin {
+    fn name(&self) -> &str {
+        "series"
+    }
+
+    fn requires(&self) -> &[&str] {
+        &["build", "posts"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
+        if site.series.is_empty() {
+            return Ok(());
+        }
+
+        // Get environment variables
+        let website_name = dotenv::var("WEBSITE_NAME").expect("WEBSITE_NAME environment variable must be set");
+        let author_name = dotenv::var("AUTHOR_NAME").expect("AUTHOR_NAME environment variable must be set");
+        let host = dotenv::var("HOST").expect("HOST environment variable must be set");
+        let website_logo_url = dotenv::var("WEBSITE_LOGO_URL").expect("WEBSITE_LOGO_URL environment variable must be set");
+
+        let series_template = fs::read_to_string(SERIES_TEMPLATE_FILE_PATH)?;
+        let post_item_template = fs::read_to_string(post_item::POST_ITEM_PARTIAL_FILE_PATH)?;
+
+        let mut rendered_pages = Vec::new();
+        for series in &site.series {
+            let mut post_items = String::new();
+            for &index in &series.posts {
+                let post = &site.posts[index];
+                let post_item = post_item::render_post_item(&post_item_template, &host, post);
+                post_items.push_str(&post_item);
+            }
+
+            let placeholders: Placeholders = vec![
+                (POST_ITEMS_PLACEHOLDER, post_items),
+                (SERIES_TITLE_PLACEHOLDER, series.name.clone()),
+                (SERIES_URL_PLACEHOLDER, permalink::url(&host, &series.permalink)),
+                (SERIES_POST_COUNT_PLACEHOLDER, series.posts.len().to_string()),
+                (HOST_PLACEHOLDER, host.clone()),
+                (WEBSITE_NAME, website_name.clone()),
+                (WEBSITE_LOGO_URL, website_logo_url.clone()),
+                (AUTHOR_NAME, author_name.clone()),
+            ];
+            rendered_pages.push((format!("{}/index.html", series.permalink), placeholders));
+        }
+
+        for (path, placeholders) in rendered_pages {
+            site.add_html_page(path, series_template.clone(), placeholders);
+        }
+        Ok(())
+    }
+}
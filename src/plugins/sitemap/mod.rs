@@ -1,17 +1,37 @@
-use std::env;
-use std::fs::File;
-use std::io::Write;
-
 use dotenv;
 
-use crate::plugin::{Plugin, Site};
+use crate::i18n;
+use crate::pagination;
+use crate::permalink;
+use crate::plugin::{Plugin, PluginError, Site};
 
+/// Generates a sitemap for every language, linking translations to each other
 pub struct SitemapPlugin;
 
 impl SitemapPlugin {
     pub fn new() -> Self {
         SitemapPlugin
     }
+
+    /// Renders a sitemap entry. `alternates` lists the (language, path) of every
+    /// version of the page, and is left out when there is only one.
+    fn url_entry(host: &str, path: &str, alternates: &[(&str, &str)]) -> String {
+        let mut links = String::new();
+        if alternates.len() > 1 {
+            for (lang, alternate) in alternates {
+                links.push_str(&format!(
+                    r#"<xhtml:link rel="alternate" hreflang="{}" href="{}"/>"#,
+                    lang,
+                    permalink::url(host, alternate)
+                ));
+            }
+        }
+        format!(
+            "<url><loc>{}</loc>{}<changefreq>weekly</changefreq><priority>0.5</priority></url>",
+            permalink::url(host, path),
+            links
+        )
+    }
 }
 
 impl Plugin for SitemapPlugin {
@@ -19,51 +39,78 @@ impl Plugin for SitemapPlugin {
         "sitemap"
     }
 
-    fn run(&self, site: &mut Site) -> Result<(), Box<dyn std::error::Error>> {
+    fn requires(&self) -> &[&str] {
+        &["build", "posts", "pages", "archives"]
+    }
+
+    fn run(&self, site: &mut Site) -> Result<(), PluginError> {
         let host = dotenv::var("HOST").expect("HOST environment variable must be set");
+        let languages = i18n::languages();
+        let default_language = &languages[0];
 
-        // Build URL entries for all posts
-        let mut url_entries = String::new();
-        for post in &site.posts {
-            url_entries.push_str(&format!(
-                "<url><loc>{}/{}/</loc><changefreq>weekly</changefreq><priority>0.5</priority></url>",
-                host, post.permalink
-            ));
-        }
+        // Every language has a homepage and a search page
+        let localized = |path: &str| -> Vec<(String, String)> {
+            languages
+                .iter()
+                .map(|lang| (lang.clone(), i18n::localize(lang, path, default_language)))
+                .collect()
+        };
+        let (homepages, search_pages) = (localized(""), localized("search"));
+        let homepage_alternates: Vec<(&str, &str)> =
+            homepages.iter().map(|(lang, path)| (lang.as_str(), path.as_str())).collect();
+        let search_alternates: Vec<(&str, &str)> =
+            search_pages.iter().map(|(lang, path)| (lang.as_str(), path.as_str())).collect();
 
-        // Add homepage
-        url_entries.push_str(&format!(
-            "<url><loc>{}</loc><changefreq>weekly</changefreq><priority>0.5</priority></url>",
-            host
-        ));
-
-        // Add all pages
-        for page in &site.pages {
-            url_entries.push_str(&format!(
-                "<url><loc>{}/{}/</loc><changefreq>weekly</changefreq><priority>0.5</priority></url>",
-                host, page.slug
-            ));
+        let mut sitemaps = Vec::new();
+        for (index, lang) in languages.iter().enumerate() {
+            // Build URL entries for all posts
+            let mut url_entries = String::new();
+            for post in site.listed_posts_in(lang) {
+                let translations = site.post_translations(post, &languages);
+                url_entries.push_str(&Self::url_entry(&host, &post.permalink, &translations));
+            }
+
+            // Add homepage
+            url_entries.push_str(&Self::url_entry(&host, &homepages[index].1, &homepage_alternates));
+
+            // Add all pages
+            for page in site.listed_pages().filter(|page| &page.content.lang == lang) {
+                let translations = site.page_translations(page, &languages);
+                url_entries.push_str(&Self::url_entry(&host, &page.permalink, &translations));
+            }
+
+            // Add series index pages
+            for series in site.series.iter().filter(|series| &series.lang == lang) {
+                url_entries.push_str(&Self::url_entry(&host, &series.permalink, &[]));
+            }
+
+            // Add every page of the date archives
+            for archive in site.archives.iter().filter(|archive| &archive.lang == lang) {
+                for number in 1..=archive.page_count {
+                    let path = pagination::page_path(&archive.permalink, number);
+                    url_entries.push_str(&Self::url_entry(&host, &path, &[]));
+                }
+            }
+
+            // Add search page
+            url_entries.push_str(&Self::url_entry(&host, &search_pages[index].1, &search_alternates));
+
+            // Build the complete sitemap XML, hreflang links need the xhtml namespace
+            let namespaces = if languages.len() > 1 {
+                r#" xmlns:xhtml="http://www.w3.org/1999/xhtml""#
+            } else {
+                ""
+            };
+            let sitemap_xml = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"{}>{}</urlset>"#,
+                namespaces, url_entries
+            );
+            sitemaps.push((i18n::localize(lang, "sitemap.xml", default_language), sitemap_xml));
         }
 
-        // Add search page
-        url_entries.push_str(&format!(
-            "<url><loc>{}/search/</loc><changefreq>weekly</changefreq><priority>0.5</priority></url>",
-            host
-        ));
-
-        // Build the complete sitemap XML
-        let sitemap_xml = format!(
-            r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">{}</urlset>"#,
-            url_entries
-        );
-
-        // Write the sitemap file
-        let current_path = env::current_dir()?.into_os_string();
-        let current_path_str = current_path.to_str().ok_or("Invalid path")?;
-        let sitemap_path = format!("{}/build/sitemap.xml", current_path_str);
-
-        let mut file = File::create(sitemap_path)?;
-        write!(file, "{}", sitemap_xml)?;
+        for (path, sitemap_xml) in sitemaps {
+            site.add_file(path, sitemap_xml);
+        }
 
         Ok(())
     }
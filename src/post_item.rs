@@ -0,0 +1,55 @@
+use crate::parser::Post;
+use crate::permalink;
+use crate::plugin;
+use crate::render;
+
+/// Partial rendering one post in a listing: homepage, archives, series and related posts
+pub const POST_ITEM_PARTIAL_FILE_PATH: &str = "./assets/templates/archive-item.html";
+
+// Post item partial placeholders
+const POST_ITEM_LINK_PLACEHOLDER: &str = "{post_link}";
+const POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER: &str = "{post_date_timestamp}";
+const POST_ITEM_DATE_READABLE_PLACEHOLDER: &str = "{post_date_human_readable}";
+const POST_ITEM_TITLE_PLACEHOLDER: &str = "{post_title}";
+const POST_ITEM_EXCERPT_PLACEHOLDER: &str = "{post_excerpt}";
+const POST_ITEM_READING_TIME_PLACEHOLDER: &str = "{post_reading_time}";
+const POST_ITEM_WORD_COUNT_PLACEHOLDER: &str = "{post_word_count}";
+
+/// Renders one post with the post item partial. Placeholders are filled in
+/// one pass, so a title or excerpt showing placeholder syntax is kept as is.
+pub fn render_post_item(template: &str, host: &str, post: &Post) -> String {
+    let frontmatter = &post.content.frontmatter;
+    plugin::fill_placeholders(
+        template,
+        &[
+            (POST_ITEM_LINK_PLACEHOLDER, permalink::url(host, &post.permalink)),
+            (POST_ITEM_DATE_TIMESTAMP_PLACEHOLDER, frontmatter.date.clone()),
+            // Human readable date (YYYY/MM/DD)
+            (POST_ITEM_DATE_READABLE_PLACEHOLDER, frontmatter.date.replace('-', "/")),
+            (POST_ITEM_TITLE_PLACEHOLDER, frontmatter.title.clone()),
+            (POST_ITEM_EXCERPT_PLACEHOLDER, render::escape(post.content.summary())),
+            (POST_ITEM_READING_TIME_PLACEHOLDER, post.content.reading_time.to_string()),
+            (POST_ITEM_WORD_COUNT_PLACEHOLDER, post.content.word_count.to_string()),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_post_item() {
+        let mut post = Post::for_test(
+            "---\ntitle: Using {post_excerpt} in templates\ndate: 2024-03-01\ndescription: Test\n---\n\nBody.\n",
+            "posts/placeholders.md",
+        );
+        post.permalink = "placeholders".to_string();
+        let template = "<a href=\"{post_link}\">{post_title}</a> {post_date_human_readable} <p>{post_excerpt}</p>";
+
+        assert_eq!(
+            render_post_item(template, "https://example.com", &post),
+            "<a href=\"https://example.com/placeholders/\">Using {post_excerpt} in templates</a> 2024/03/01 <p>Test</p>"
+        );
+    }
+}
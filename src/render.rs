@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use markdown::mdast::{AlignKind, Definition, List, ListItem, Node, Table};
+
+/// Protocols allowed in link and image URLs, anything else is dropped
+const SAFE_PROTOCOLS: [&str; 6] = ["http", "https", "mailto", "irc", "ircs", "xmpp"];
+
+/// Renders a markdown syntax tree to HTML.
+///
+/// Raw HTML in the document is passed through untouched, for the mixed
+/// Markdown + HTML post style. GFM tables, strikethrough, task lists and
+/// footnotes are rendered the same way as `markdown::to_html_with_options`.
+pub fn to_html(tree: &Node) -> String {
+    let mut renderer = Renderer::default();
+    renderer.collect_definitions(tree);
+
+    let mut html = renderer.render(tree);
+    html.push_str(&renderer.render_footnotes());
+    html
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn sanitize_url(url: &str) -> String {
+    let protocol_end = url.find(':');
+    let path_start = url.find(['/', '?', '#']);
+    if let Some(end) = protocol_end {
+        // A colon after the first slash, question mark or hash is not a protocol
+        let is_protocol = path_start.is_none_or(|start| end < start);
+        if is_protocol && !SAFE_PROTOCOLS.contains(&url[..end].to_lowercase().as_str()) {
+            return String::new();
+        }
+    }
+    escape(&markdown::sanitize(url))
+}
+
+fn title_attribute(title: &Option<String>) -> String {
+    match title {
+        Some(title) => format!(r#" title="{}""#, escape(title)),
+        None => String::new(),
+    }
+}
+
+#[derive(Default)]
+struct Renderer {
+    definitions: HashMap<String, Definition>,
+    /// Footnote definitions by identifier, rendered at the end
+    footnote_definitions: HashMap<String, Vec<Node>>,
+    /// Footnotes in the order they are first referenced
+    footnote_order: Vec<String>,
+}
+
+impl Renderer {
+    fn collect_definitions(&mut self, node: &Node) {
+        match node {
+            Node::Definition(definition) => {
+                self.definitions
+                    .entry(definition.identifier.clone())
+                    .or_insert_with(|| definition.clone());
+            }
+            Node::FootnoteDefinition(definition) => {
+                self.footnote_definitions
+                    .entry(definition.identifier.clone())
+                    .or_insert_with(|| definition.children.clone());
+            }
+            _ => {}
+        }
+        if let Some(children) = node.children() {
+            for child in children {
+                self.collect_definitions(child);
+            }
+        }
+    }
+
+    fn render_children(&mut self, children: &[Node]) -> String {
+        children.iter().map(|child| self.render(child)).collect()
+    }
+
+    /// Block children are separated by newlines, like the reference renderer
+    fn render_blocks(&mut self, children: &[Node]) -> String {
+        children
+            .iter()
+            .map(|child| self.render(child))
+            .filter(|html| !html.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn render(&mut self, node: &Node) -> String {
+        match node {
+            Node::Root(root) => self.render_blocks(&root.children),
+            Node::Paragraph(paragraph) => format!("<p>{}</p>", self.render_children(&paragraph.children)),
+            Node::Heading(heading) => format!(
+                "<h{depth}>{}</h{depth}>",
+                self.render_children(&heading.children),
+                depth = heading.depth
+            ),
+            Node::Blockquote(blockquote) => {
+                format!("<blockquote>\n{}\n</blockquote>", self.render_blocks(&blockquote.children))
+            }
+            Node::List(list) => self.render_list(list),
+            Node::Code(code) => {
+                let class = match code.lang.as_deref() {
+                    Some(lang) => format!(r#" class="language-{}""#, escape(lang)),
+                    None => String::new(),
+                };
+                let value = if code.value.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}\n", escape(&code.value))
+                };
+                format!("<pre><code{}>{}</code></pre>", class, value)
+            }
+            Node::Math(math) => format!(
+                r#"<pre><code class="language-math math-display">{}</code></pre>"#,
+                escape(&math.value)
+            ),
+            Node::Html(html) => html.value.clone(),
+            Node::ThematicBreak(_) => "<hr />".to_string(),
+            Node::Table(table) => self.render_table(table),
+            Node::Text(text) => escape(&text.value),
+            Node::Emphasis(emphasis) => format!("<em>{}</em>", self.render_children(&emphasis.children)),
+            Node::Strong(strong) => format!("<strong>{}</strong>", self.render_children(&strong.children)),
+            Node::Delete(delete) => format!("<del>{}</del>", self.render_children(&delete.children)),
+            Node::InlineCode(code) => format!("<code>{}</code>", escape(&code.value)),
+            Node::InlineMath(math) => format!(
+                r#"<code class="language-math math-inline">{}</code>"#,
+                escape(&math.value)
+            ),
+            Node::Break(_) => "<br />\n".to_string(),
+            Node::Link(link) => format!(
+                r#"<a href="{}"{}>{}</a>"#,
+                sanitize_url(&link.url),
+                title_attribute(&link.title),
+                self.render_children(&link.children)
+            ),
+            Node::Image(image) => format!(
+                r#"<img src="{}" alt="{}"{} />"#,
+                sanitize_url(&image.url),
+                escape(&image.alt),
+                title_attribute(&image.title)
+            ),
+            Node::LinkReference(reference) => {
+                let children = self.render_children(&reference.children);
+                match self.definitions.get(&reference.identifier) {
+                    Some(definition) => format!(
+                        r#"<a href="{}"{}>{}</a>"#,
+                        sanitize_url(&definition.url),
+                        title_attribute(&definition.title),
+                        children
+                    ),
+                    None => children,
+                }
+            }
+            Node::ImageReference(reference) => match self.definitions.get(&reference.identifier) {
+                Some(definition) => format!(
+                    r#"<img src="{}" alt="{}"{} />"#,
+                    sanitize_url(&definition.url),
+                    escape(&reference.alt),
+                    title_attribute(&definition.title)
+                ),
+                None => escape(&reference.alt),
+            },
+            Node::FootnoteReference(reference) => self.render_footnote_reference(&reference.identifier),
+            // Rendered elsewhere or not rendered at all
+            Node::Definition(_)
+            | Node::FootnoteDefinition(_)
+            | Node::Yaml(_)
+            | Node::Toml(_)
+            | Node::MdxjsEsm(_)
+            | Node::MdxFlowExpression(_)
+            | Node::MdxTextExpression(_)
+            | Node::MdxJsxFlowElement(_)
+            | Node::MdxJsxTextElement(_) => String::new(),
+            // Only reachable through their parents
+            Node::ListItem(_) | Node::TableRow(_) | Node::TableCell(_) => String::new(),
+        }
+    }
+
+    fn render_list(&mut self, list: &List) -> String {
+        // A list is loose if it or any of its items is spread
+        let loose = list.spread
+            || list
+                .children
+                .iter()
+                .any(|child| matches!(child, Node::ListItem(item) if item.spread));
+
+        let items: Vec<String> = list
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                Node::ListItem(item) => Some(self.render_list_item(item, loose)),
+                _ => None,
+            })
+            .collect();
+
+        if list.ordered {
+            let start = match list.start {
+                Some(start) if start != 1 => format!(r#" start="{}""#, start),
+                _ => String::new(),
+            };
+            format!("<ol{}>\n{}\n</ol>", start, items.join("\n"))
+        } else {
+            format!("<ul>\n{}\n</ul>", items.join("\n"))
+        }
+    }
+
+    fn render_list_item(&mut self, item: &ListItem, loose: bool) -> String {
+        let checkbox = match item.checked {
+            Some(true) => r#"<input type="checkbox" disabled="" checked="" /> "#,
+            Some(false) => r#"<input type="checkbox" disabled="" /> "#,
+            None => "",
+        };
+
+        if loose {
+            let mut content = self.render_blocks(&item.children);
+            if !checkbox.is_empty() {
+                content = content.replacen("<p>", &format!("<p>{}", checkbox), 1);
+            }
+            return format!("<li>\n{}\n</li>", content);
+        }
+
+        // Tight lists render their paragraphs without <p>, other blocks
+        // go on lines of their own
+        let mut content = String::new();
+        for child in &item.children {
+            match child {
+                Node::Paragraph(paragraph) => content.push_str(&self.render_children(&paragraph.children)),
+                other => {
+                    if !content.ends_with('\n') {
+                        content.push('\n');
+                    }
+                    content.push_str(&self.render(other));
+                    content.push('\n');
+                }
+            }
+        }
+        format!("<li>{}{}</li>", checkbox, content)
+    }
+
+    fn render_table(&mut self, table: &Table) -> String {
+        let mut rows = table.children.iter().filter_map(|child| match child {
+            Node::TableRow(row) => Some(row),
+            _ => None,
+        });
+
+        let mut html = String::from("<table>\n");
+        if let Some(head) = rows.next() {
+            html.push_str("<thead>\n");
+            html.push_str(&self.render_table_row(&head.children, &table.align, "th"));
+            html.push_str("</thead>\n");
+        }
+
+        let body: Vec<String> = rows
+            .map(|row| self.render_table_row(&row.children, &table.align, "td"))
+            .collect();
+        if !body.is_empty() {
+            html.push_str("<tbody>\n");
+            html.push_str(&body.concat());
+            html.push_str("</tbody>\n");
+        }
+
+        html.push_str("</table>");
+        html
+    }
+
+    fn render_table_row(&mut self, cells: &[Node], align: &[AlignKind], tag: &str) -> String {
+        let mut html = String::from("<tr>\n");
+        for (index, cell) in cells.iter().enumerate() {
+            let alignment = match align.get(index) {
+                Some(AlignKind::Left) => r#" align="left""#,
+                Some(AlignKind::Right) => r#" align="right""#,
+                Some(AlignKind::Center) => r#" align="center""#,
+                _ => "",
+            };
+            let content = match cell {
+                Node::TableCell(cell) => self.render_children(&cell.children),
+                _ => String::new(),
+            };
+            html.push_str(&format!("<{tag}{}>{}</{tag}>\n", alignment, content, tag = tag));
+        }
+        html.push_str("</tr>\n");
+        html
+    }
+
+    fn render_footnote_reference(&mut self, identifier: &str) -> String {
+        let index = match self.footnote_order.iter().position(|id| id == identifier) {
+            Some(index) => index + 1,
+            None => {
+                self.footnote_order.push(identifier.to_string());
+                self.footnote_order.len()
+            }
+        };
+        let id = escape(identifier);
+        format!(
+            r##"<sup><a href="#user-content-fn-{id}" id="user-content-fnref-{id}" data-footnote-ref="" aria-describedby="footnote-label">{index}</a></sup>"##,
+            id = id,
+            index = index
+        )
+    }
+
+    fn render_footnotes(&mut self) -> String {
+        if self.footnote_order.is_empty() {
+            return String::new();
+        }
+
+        let mut items = Vec::new();
+        let order = self.footnote_order.clone();
+        for identifier in &order {
+            let children = self.footnote_definitions.get(identifier).cloned().unwrap_or_default();
+            let id = escape(identifier);
+            let back_reference = format!(
+                r##"<a href="#user-content-fnref-{}" data-footnote-backref="" aria-label="Back to content" class="data-footnote-backref">↩</a>"##,
+                id
+            );
+
+            let mut content = self.render_blocks(&children);
+            match content.rfind("</p>") {
+                Some(position) if content.ends_with("</p>") => {
+                    content.insert_str(position, &format!(" {}", back_reference))
+                }
+                _ => content.push_str(&back_reference),
+            }
+            items.push(format!("<li id=\"user-content-fn-{}\">\n{}\n</li>", id, content));
+        }
+
+        format!(
+            "\n<section data-footnotes=\"\" class=\"footnotes\"><h2 id=\"footnote-label\" class=\"sr-only\">Footnotes</h2>\n<ol>\n{}\n</ol>\n</section>\n",
+            items.join("\n")
+        )
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::NaiveDate;
+
+use crate::parser::{self, ContentKind, FrontmatterMap};
+
+/// The kind of value a frontmatter field holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// Any scalar, like `title: 1984`
+    String,
+    /// YYYY-MM-DD, optionally followed by a time
+    Date,
+    /// `true` or `false`
+    Boolean,
+    Number,
+    /// A single string or a list of strings
+    List,
+}
+
+impl FieldType {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "string" => Ok(FieldType::String),
+            "date" => Ok(FieldType::Date),
+            "boolean" => Ok(FieldType::Boolean),
+            "number" => Ok(FieldType::Number),
+            "list" => Ok(FieldType::List),
+            _ => Err(format!("Unknown field type {}, use string, date, boolean, number or list", name)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            FieldType::String => "a string",
+            FieldType::Date => "a YYYY-MM-DD date",
+            FieldType::Boolean => "true or false",
+            FieldType::Number => "a number",
+            FieldType::List => "a string or a list of strings",
+        }
+    }
+
+    fn accepts(&self, value: &serde_yaml::Value) -> bool {
+        use serde_yaml::Value;
+        match (self, value) {
+            (FieldType::String, Value::String(_) | Value::Number(_) | Value::Bool(_)) => true,
+            (FieldType::Date, Value::String(date)) => NaiveDate::parse_from_str(parser::day_of(date), "%Y-%m-%d").is_ok(),
+            (FieldType::Boolean, Value::Bool(_)) => true,
+            (FieldType::Boolean, Value::String(flag)) => flag == "true" || flag == "false",
+            (FieldType::Number, Value::Number(_)) => true,
+            (FieldType::Number, Value::String(number)) => number.parse::<f64>().is_ok(),
+            (FieldType::List, Value::Sequence(values)) => values.iter().all(Value::is_string),
+            (FieldType::List, value) => FieldType::String.accepts(value),
+            _ => false,
+        }
+    }
+}
+
+/// Which content must set a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Required {
+    Never,
+    Posts,
+    Always,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub field_type: FieldType,
+    pub required: Required,
+}
+
+/// The fields the generator understands
+const BUILT_IN_FIELDS: [(&str, FieldType, Required); 17] = [
+    ("aliases", FieldType::List, Required::Never),
+    ("date", FieldType::Date, Required::Posts),
+    ("description", FieldType::String, Required::Never),
+    ("expires", FieldType::Date, Required::Never),
+    ("image", FieldType::String, Required::Never),
+    ("keywords", FieldType::String, Required::Never),
+    ("lang", FieldType::String, Required::Never),
+    ("layout", FieldType::String, Required::Never),
+    ("lightTheme", FieldType::Boolean, Required::Never),
+    ("math", FieldType::Boolean, Required::Never),
+    ("permalink", FieldType::String, Required::Never),
+    ("publish", FieldType::String, Required::Never),
+    ("series", FieldType::String, Required::Never),
+    ("seriesOrder", FieldType::Number, Required::Never),
+    ("tags", FieldType::List, Required::Never),
+    ("title", FieldType::String, Required::Always),
+    ("translationKey", FieldType::String, Required::Never),
+];
+
+/// The frontmatter fields posts and pages may set
+#[derive(Debug)]
+pub struct Schema {
+    fields: Vec<Field>,
+}
+
+impl Schema {
+    /// The built-in fields plus the ones listed in `FRONTMATTER_FIELDS`
+    pub fn from_env() -> Result<Self, String> {
+        Self::with_extra_fields(&dotenv::var("FRONTMATTER_FIELDS").unwrap_or_default())
+    }
+
+    /// Adds comma-separated `name:type` fields to the built-in ones, with a
+    /// `:required` suffix for fields every post and page must set
+    pub fn with_extra_fields(extra_fields: &str) -> Result<Self, String> {
+        let mut fields: Vec<Field> = BUILT_IN_FIELDS
+            .iter()
+            .map(|&(name, field_type, required)| Field {
+                name: name.to_string(),
+                field_type,
+                required,
+            })
+            .collect();
+
+        for definition in extra_fields.split(',').map(str::trim).filter(|definition| !definition.is_empty()) {
+            let mut parts = definition.split(':').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let field_type = FieldType::parse(parts.next().unwrap_or("string"))
+                .map_err(|error| format!("FRONTMATTER_FIELDS: {}", error))?;
+            let required = match parts.next() {
+                None => Required::Never,
+                Some("required") => Required::Always,
+                Some(other) => return Err(format!("FRONTMATTER_FIELDS: expected \"required\" after {}, got \"{}\"", name, other)),
+            };
+            if fields.iter().any(|field| field.name == name) {
+                return Err(format!("FRONTMATTER_FIELDS: {} is already a field", name));
+            }
+            fields.push(Field {
+                name: name.to_string(),
+                field_type,
+                required,
+            });
+        }
+        Ok(Schema { fields })
+    }
+
+    /// Returns the field closest to an unknown key, to catch typos like `lightheme`
+    fn suggestion(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .map(|field| (edit_distance(&key.to_lowercase(), &field.name.to_lowercase()), &field.name))
+            .filter(|(distance, _)| *distance <= 2)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Checks the frontmatter of a post or page, returning a message per problem
+    pub fn validate(&self, frontmatter: &FrontmatterMap, kind: ContentKind) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (key, value) in frontmatter {
+            match self.fields.iter().find(|field| &field.name == key) {
+                None => problems.push(match self.suggestion(key) {
+                    Some(suggestion) => format!("unknown field {}, did you mean {}?", key, suggestion),
+                    None => format!("unknown field {}", key),
+                }),
+                Some(field) if !value.is_null() && !field.field_type.accepts(value) => {
+                    problems.push(format!("{} must be {}", key, field.field_type.name()));
+                }
+                Some(_) => {}
+            }
+        }
+
+        for field in &self.fields {
+            let required = match field.required {
+                Required::Never => false,
+                Required::Posts => kind == ContentKind::Post,
+                Required::Always => true,
+            };
+            if required && frontmatter.get(&field.name).is_none_or(serde_yaml::Value::is_null) {
+                problems.push(format!("missing required field {}", field.name));
+            }
+        }
+        problems
+    }
+}
+
+/// Levenshtein distance between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(schema: &Schema, yaml: &str, kind: ContentKind) -> Vec<String> {
+        schema.validate(&parser::parse_frontmatter_map(yaml).unwrap(), kind)
+    }
+
+    #[test]
+    fn test_validate_built_in_fields() {
+        let schema = Schema::with_extra_fields("").unwrap();
+
+        assert!(validate(&schema, "title: 1984\ndate: 2024-01-15 10:00\nlightTheme: true\ntags: rust\nseriesOrder: 2\n", ContentKind::Post).is_empty());
+        assert_eq!(
+            validate(&schema, "title: Hello\nlightheme: true\npubish: draft\nseriesOrder: first\ndate: 15/01/2024\ntags: [[a]]\n", ContentKind::Post),
+            vec![
+                "date must be a YYYY-MM-DD date",
+                "unknown field lightheme, did you mean lightTheme?",
+                "unknown field pubish, did you mean publish?",
+                "seriesOrder must be a number",
+                "tags must be a string or a list of strings",
+            ]
+        );
+        assert_eq!(validate(&schema, "description: About\n", ContentKind::Post), vec![
+            "missing required field date",
+            "missing required field title",
+        ]);
+        assert_eq!(validate(&schema, "title: About\nsubtitle: Me\n", ContentKind::Page), vec!["unknown field subtitle"]);
+    }
+
+    #[test]
+    fn test_extra_fields() {
+        let schema = Schema::with_extra_fields("subtitle, rating: number, author: string: required").unwrap();
+
+        assert!(validate(&schema, "title: About\nsubtitle: Me\nrating: 4.5\nauthor: Jane\n", ContentKind::Page).is_empty());
+        assert_eq!(
+            validate(&schema, "title: About\nrating: high\n", ContentKind::Page),
+            vec!["rating must be a number", "missing required field author"]
+        );
+
+        assert!(Schema::with_extra_fields("rating: decimal").is_err());
+        assert!(Schema::with_extra_fields("title: string").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use deunicode::deunicode;
+
+/// Turns arbitrary text into a URL-safe slug.
+///
+/// Non-Latin characters are transliterated to ASCII, quotes are dropped,
+/// `&` becomes "and" and every other run of non-alphanumeric characters
+/// collapses into a single dash.
+pub fn slugify(text: &str) -> String {
+    let transliterated = deunicode(text).to_lowercase().replace('&', " and ");
+
+    let mut slug = String::with_capacity(transliterated.len());
+    for character in transliterated.chars() {
+        if character.is_ascii_alphanumeric() {
+            slug.push(character);
+        } else if character == '\'' || character == '"' {
+            // "Don't" should become "dont", not "don-t"
+            continue;
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Normalizes a path below `build/` so that "/About/", "about" and "about/"
+/// compare equal when looking for collisions.
+pub fn normalize_path(path: &str) -> String {
+    path.trim_matches('/').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(slugify("Survey #9 (2021)"), "survey-9-2021");
+        assert_eq!(slugify("What? Why / How"), "what-why-how");
+        assert_eq!(slugify("Rock & Roll"), "rock-and-roll");
+        assert_eq!(slugify("Don't \"quote\" me"), "dont-quote-me");
+        assert_eq!(slugify("  --Leading and trailing--  "), "leading-and-trailing");
+    }
+
+    #[test]
+    fn test_slugify_transliterates() {
+        assert_eq!(slugify("Café con leche"), "cafe-con-leche");
+        assert_eq!(slugify("Ñandú über straße"), "nandu-uber-strasse");
+        assert_eq!(slugify("Привет мир"), "privet-mir");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/About/"), "about");
+        assert_eq!(normalize_path("search"), "search");
+        assert_eq!(normalize_path("docs/setup/"), "docs/setup");
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const SNAPSHOT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/site.snap");
+
+/// Configuration of the snapshot site. The build runs with only these
+/// variables set, so nothing from the environment of `cargo test` leaks in.
+const ENV: [(&str, &str); 8] = [
+    ("HOST", "https://example.com"),
+    ("WEBSITE_NAME", "Example"),
+    ("AUTHOR_NAME", "Jane Doe"),
+    ("WEBSITE_LOGO_URL", "https://example.com/img/logo.png"),
+    ("WEBSITE_DESCRIPTION", "An example site"),
+    ("TWITTER_HANDLE", "@example"),
+    ("PERMALINK_PATTERN", "/:slug/"),
+    ("REDIRECT_FILES", "netlify"),
+];
+
+fn write_file(root: &Path, path: &str, contents: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+fn copy_dir(src: &Path, dst: &Path) {
+    fs::create_dir_all(dst).unwrap();
+    for entry in fs::read_dir(src).unwrap().flatten() {
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &dst.join(entry.file_name()));
+        } else {
+            fs::copy(entry.path(), dst.join(entry.file_name())).unwrap();
+        }
+    }
+}
+
+/// Collects every file below `dir` by its path relative to `root`
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, Vec<u8>)>) {
+    for entry in fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(root, &path, files);
+        } else {
+            let relative = path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            files.push((relative, fs::read(&path).unwrap()));
+        }
+    }
+}
+
+/// Renders every file of the build into one text, in path order
+fn snapshot(build_dir: &Path) -> String {
+    let mut files = Vec::new();
+    collect_files(build_dir, build_dir, &mut files);
+    files.sort();
+    files
+        .iter()
+        .map(|(path, contents)| format!("=== {} ===\n{}\n", path, String::from_utf8_lossy(contents)))
+        .collect()
+}
+
+/// Builds a small site with the default templates in a separate process and
+/// compares every output file with `tests/snapshots/site.snap`. Run with
+/// `UPDATE_SNAPSHOTS=1` to accept a change.
+#[test]
+fn test_site_snapshot() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let root = tempfile::tempdir().unwrap();
+    copy_dir(&manifest_dir.join("assets"), &root.path().join("assets"));
+    copy_dir(&manifest_dir.join("public"), &root.path().join("public"));
+    write_file(
+        root.path(),
+        "posts/2024-01-15-hello.md",
+        "---\ntitle: Hello World\ndate: 2024-01-15\ndescription: The first post\naliases: old/hello.html\nseries: Getting Started\nseriesOrder: 2\ntags: [setup]\n---\n\nHello *world*.\n",
+    );
+    write_file(
+        root.path(),
+        "posts/2024-03-01-install.md",
+        "---\ntitle: Install\ndate: 2024-03-01\nseries: Getting Started\nseriesOrder: 1\ntags: setup\n---\n\nRun the installer, then read the [setup guide](@/pages/docs/setup.md#steps).\n",
+    );
+    write_file(
+        root.path(),
+        "posts/2024-02-01-draft.md",
+        "---\ntitle: Draft\ndate: 2024-02-01\npublish: draft\n---\n\nNot yet.\n",
+    );
+    write_file(root.path(), "pages/docs/_index.md", "---\ntitle: Docs\n---\n\nAll the docs.\n");
+    write_file(root.path(), "pages/docs/setup.md", "---\ntitle: Setup\n---\n\n1. Install\n2. Run\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_file-parsing"))
+        .current_dir(root.path())
+        .env_clear()
+        .envs(ENV)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let actual = snapshot(&root.path().join("build"));
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+        fs::create_dir_all(Path::new(SNAPSHOT_PATH).parent().unwrap()).unwrap();
+        fs::write(SNAPSHOT_PATH, &actual).unwrap();
+    }
+    let expected = fs::read_to_string(SNAPSHOT_PATH).expect("Missing snapshot, run with UPDATE_SNAPSHOTS=1");
+    assert_eq!(actual, expected);
+}